@@ -0,0 +1,147 @@
+// Throughput del ThreadPool (canal MPMC de crossbeam) contra el diseño anterior (todos los workers
+// compartiendo un Arc<Mutex<mpsc::Receiver>>). El pool anterior hace lo mismo por job que el actual
+// (mide la espera en la cola y aísla los panics), así la diferencia es solo el despacho de los jobs.
+// La contención aparece con varios CPUs: con uno solo los workers no compiten por la cola.
+// Correr con: cargo bench --bench pool
+extern crate httprust;
+
+use std::{
+    hint::black_box,
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use httprust::http::pool::ThreadPool;
+
+// Jobs por medición y repeticiones (se reporta la mejor)
+const JOBS: usize = 200_000;
+const ROUNDS: usize = 5;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// Pool como era antes: un solo receiver protegido por un Mutex
+struct LegacyPool {
+    sender: Option<mpsc::Sender<(Job, Instant)>>,
+    workers: Vec<thread::JoinHandle<()>>,
+    queue_wait_nanos: Arc<AtomicU64>,
+}
+
+impl LegacyPool {
+    fn new(size: usize) -> LegacyPool {
+        let (sender, receiver) = mpsc::channel::<(Job, Instant)>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let queue_wait_nanos = Arc::new(AtomicU64::new(0));
+        let workers = (0..size)
+            .map(|_| {
+                let (receiver, queue_wait_nanos) = (Arc::clone(&receiver), Arc::clone(&queue_wait_nanos));
+                thread::spawn(move || loop {
+                    let message = receiver.lock().unwrap().recv();
+                    match message {
+                        Ok((job, queued_at)) => {
+                            queue_wait_nanos.fetch_add(queued_at.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        LegacyPool { sender: Some(sender), workers, queue_wait_nanos }
+    }
+
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        self.sender.as_ref().unwrap().send((Box::new(job), Instant::now())).unwrap();
+    }
+}
+
+impl Drop for LegacyPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+        black_box(self.queue_wait_nanos.load(Ordering::Relaxed));
+    }
+}
+
+// Lo mínimo que necesita el benchmark de cada pool
+trait Pool: Sync {
+    fn run(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+impl Pool for LegacyPool {
+    fn run(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        self.execute(job);
+    }
+}
+
+impl Pool for ThreadPool {
+    fn run(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        self.execute(job);
+    }
+}
+
+// Trabajo de cada job: `spins` iteraciones de un cálculo que el compilador no puede quitar
+fn work(spins: u64) {
+    let mut x = 0u64;
+    for i in 0..spins {
+        x = black_box(x.wrapping_mul(31).wrapping_add(i));
+    }
+    black_box(x);
+}
+
+// Encola JOBS jobs desde `producers` threads (como varios listeners) y espera a que terminen
+fn measure<P: Pool>(pool: P, producers: usize, spins: u64) -> Duration {
+    let done = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..producers {
+            let (pool, done) = (&pool, &done);
+            scope.spawn(move || {
+                for _ in 0..JOBS / producers {
+                    let done = Arc::clone(done);
+                    pool.run(Box::new(move || {
+                        work(spins);
+                        done.fetch_add(1, Ordering::Relaxed);
+                    }));
+                }
+            });
+        }
+    });
+    // Al soltar el pool se esperan los jobs que quedan en la cola
+    drop(pool);
+    let elapsed = start.elapsed();
+    assert_eq!(done.load(Ordering::Relaxed), JOBS / producers * producers);
+    elapsed
+}
+
+fn best<F: FnMut() -> Duration>(mut run: F) -> Duration {
+    (0..ROUNDS).map(|_| run()).min().unwrap()
+}
+
+fn main() {
+    let cpus = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    println!("{} jobs per run, best of {} runs, {} CPUs\n", JOBS, ROUNDS, cpus);
+    println!("{:>8} {:>10} {:>8} {:>16} {:>16} {:>8}", "workers", "producers", "spins", "legacy jobs/s", "pool jobs/s", "speedup");
+
+    for &workers in &[4, 16, 64] {
+        for &producers in &[1, 4] {
+            for &spins in &[0, 1000] {
+                let legacy = best(|| measure(LegacyPool::new(workers), producers, spins));
+                let pool = best(|| measure(ThreadPool::new(workers), producers, spins));
+                let rate = |elapsed: Duration| JOBS as f64 / elapsed.as_secs_f64();
+                println!(
+                    "{:>8} {:>10} {:>8} {:>16.0} {:>16.0} {:>7.2}x",
+                    workers,
+                    producers,
+                    spins,
+                    rate(legacy),
+                    rate(pool),
+                    legacy.as_secs_f64() / pool.as_secs_f64()
+                );
+            }
+        }
+    }
+}
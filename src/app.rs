@@ -1,8 +1,27 @@
 use crate::http::parser::{Body, Request, Response, create_response};
-use std::sync::{Arc, RwLock, atomic::{AtomicU32, Ordering}};
+pub mod api_keys;
+pub mod tokens;
+pub mod users;
+use crate::app::api_keys::{ApiKeyInfo, Scope};
+use crate::config::{AppConfig, LogConfig};
+use crate::app::users::{AuthError, RegisterError, Role, RoleError};
+use crate::http::auth::{ApiKeyAuth, Identity};
+use crate::http::access_log::{AccessLog, LogFormat, RotatingFile, StdoutSink};
+use crate::http::cors::Cors;
+use crate::http::middleware::{Middleware, Next};
+use crate::http::secure_cookies::{CookieKeys, CookiePolicy};
+use crate::http::session::{FileStore, MemoryStore, SessionMiddleware};
+use crate::http::sse::{Event, EventBuffer, EventStream};
+use crate::http::websocket::{Message as SocketMessage, WebSocket, CLOSE_UNSUPPORTED_DATA};
+use std::sync::{Arc, LockResult, RwLock, atomic::{AtomicU32, Ordering}};
 use std::collections::HashMap;
+use std::time::Duration;
 extern crate lazy_static;
 use app::lazy_static::lazy_static;
+extern crate rand;
+use app::rand::Rng;
+extern crate tracing;
+use app::tracing::{error, info, warn};
 
 #[derive(Clone)]
 struct Message {
@@ -16,14 +35,32 @@ lazy_static! {
     static ref MESSAGES: Arc<RwLock<HashMap<u32, Message>>> = Arc::new(RwLock::new(HashMap::new()));
 }
 
+// Eventos de cambios en los mensajes (para GET /msg/events), se guardan los últimos 100
+lazy_static! {
+    static ref MESSAGE_EVENTS: EventBuffer = EventBuffer::new(100);
+}
+
+// Cada cuánto se envía un comentario para mantener viva la conexión del stream
+const EVENTS_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
 // Variable global para el id (con AtomicU32)
 lazy_static! {
     static ref NEXT_ID: AtomicU32 = AtomicU32::new(1); // Inicializamos en 1
 }
 
+// Un controller que hace panic con el lock de los mensajes tomado lo envenena. Los mensajes se siguen
+// usando (cada cambio es una sola operación sobre el HashMap) y se quita la marca para el check de readiness.
+fn recover<G>(result: LockResult<G>) -> G {
+    result.unwrap_or_else(|poisoned| {
+        warn!("Message store lock was poisoned by a panic, recovering");
+        MESSAGES.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
 // Función para leer todos los mensajes de la variable global
 fn get_messages() -> Vec<Message> {
-    let messages = MESSAGES.read().unwrap(); // Bloquea para lectura
+    let messages = recover(MESSAGES.read()); // Bloquea para lectura
     messages.iter()
         .map(|(&id, message)| Message { id, content: message.content.clone(), username: message.username.clone() })
         .collect() // Devuelve una copia de los mensajes
@@ -31,25 +68,29 @@ fn get_messages() -> Vec<Message> {
 
 // Función para leer un mensaje por id
 fn get_message(id: u32) -> Option<Message> {
-    let messages = MESSAGES.read().unwrap(); // Bloquea para lectura
+    let messages = recover(MESSAGES.read()); // Bloquea para lectura
     messages.get(&id).cloned() // Devuelve una copia del valor
 }
 
 // Función para escribir datos en la variable global
 fn add_message(content: String, username: String) -> u32 {
     let id = get_next_id(); // Obtiene un nuevo id
-    let mut messages = MESSAGES.write().unwrap(); // Bloquea para escritura
+    let mut messages = recover(MESSAGES.write()); // Bloquea para escritura
+    let data = serde_json::json!({ "id": id, "content": content, "username": username }).to_string();
     let message = Message { id, content, username }; // Crea el nuevo mensaje
     messages.insert(id, message); // Inserta el mensaje
+    MESSAGE_EVENTS.push("created", data); // Notifica a los streams
     id
 }
 
 // Función para actualizar un mensaje por id
 fn edit_existing_message(id: u32, new_content: String) -> Result<String, String> {
-    let mut messages = MESSAGES.write().unwrap(); // Bloquea para escritura
+    let mut messages = recover(MESSAGES.write()); // Bloquea para escritura
     
     if let Some(message) = messages.get_mut(&id) { // Actualiza el mensaje si existe
         message.content = new_content.clone();
+        let data = serde_json::json!({ "id": id, "content": message.content, "username": message.username }).to_string();
+        MESSAGE_EVENTS.push("updated", data); // Notifica a los streams
         Ok(format!("Message with ID {} updated", id))
     } else {
         Err(format!("Message with ID {} not found", id))
@@ -58,10 +99,11 @@ fn edit_existing_message(id: u32, new_content: String) -> Result<String, String>
 
 // Función para eliminar datos de la variable global
 fn delete_message(id: u32) -> Result<String, String> {
-    let mut messages = MESSAGES.write().unwrap(); // Bloquea para escritura
+    let mut messages = recover(MESSAGES.write()); // Bloquea para escritura
 
     if messages.remove(&id).is_some() { // Elimina el mensaje si existe
-        println!("Message with ID {} deleted", id);
+        info!("Message with ID {} deleted", id);
+        MESSAGE_EVENTS.push("deleted", serde_json::json!({ "id": id }).to_string()); // Notifica a los streams
         Ok(format!("Message with ID {} deleted", id))
     } else {
         Err("Message not found".to_string())
@@ -73,37 +115,282 @@ fn get_next_id() -> u32 {
     NEXT_ID.fetch_add(1, Ordering::SeqCst) // Incrementa el id
 }
 
-// Controller para el login
-pub fn login_controller(req: Request) -> Response {
-    // Obtener el username desde las cookies
-    let username = match req.body {
-        Some(Body::Text(ref text)) => {
-            text.to_string()
+// Check de readiness: el lock de los mensajes no quedó envenenado por un panic (hasta que el siguiente acceso lo recupere)
+pub fn messages_check() -> Result<(), String> {
+    if MESSAGES.is_poisoned() {
+        return Err("Message store lock is poisoned".to_string());
+    }
+    Ok(())
+}
+
+// Configuración de la app, se define al iniciar con configure()
+lazy_static! {
+    static ref SETTINGS: RwLock<AppConfig> = RwLock::new(AppConfig::default());
+}
+
+// Guarda la configuración de la app. Se debe llamar antes de crear los middlewares y de atender requests.
+pub fn configure(config: &AppConfig) {
+    *SETTINGS.write().unwrap() = config.clone();
+}
+
+fn settings() -> AppConfig {
+    SETTINGS.read().unwrap().clone()
+}
+
+// Crea las cuentas de admin de app.admins ("usuario:contraseña"). Se llama al iniciar, después de configure().
+// Los demás admins los nombra un admin con PATCH /admin/users.
+pub fn create_admins() -> Result<(), String> {
+    for admin in settings().admins {
+        let (username, password) = match admin.split_once(':') {
+            Some(credentials) => credentials,
+            None => return Err(format!("app.admins: '{}' must be 'username:password'", admin)),
+        };
+        match users::create_admin(username, password) {
+            Ok(_) => info!("Admin account created: {}", username),
+            Err(RegisterError::UsernameTaken) => return Err(format!("app.admins: '{}' is listed more than once", username)),
+            Err(RegisterError::Invalid(msg)) => return Err(format!("app.admins: '{}': {}", username, msg)),
         }
-        Some(Body::Json(ref json_value)) => {
-            match json_value.get("username") {
-                Some(message) => message.as_str().unwrap_or("").to_string(),
-                None => return create_response(400, Some("Missing 'username' in JSON body".to_string()), None::<HashMap<String, String>>),
+    }
+    Ok(())
+}
+
+// Política de cookies de la app: la cookie de sesión va firmada para que no se pueda falsificar.
+// Con varios secretos (app.cookie_secrets) el primero firma y los demás se aceptan para rotar llaves;
+// si no hay ninguno se genera uno aleatorio y las cookies dejan de ser válidas al reiniciar.
+pub fn cookie_policy() -> CookiePolicy {
+    let secrets = settings().cookie_secrets;
+    let keys = match secrets.split_first() {
+        Some((current, previous)) => {
+            if current.len() < 32 {
+                warn!("The cookie secret should be at least 32 characters long");
             }
+            previous.iter().fold(CookieKeys::new(current.as_bytes()), |keys, secret| keys.with_previous(secret.as_bytes()))
         }
-        _ => return create_response(400, Some("Invalid request body".to_string()), None::<HashMap<String, String>>),
+        None => {
+            warn!("HTTPRUST_COOKIE_SECRET not set, using a random secret");
+            let secret: [u8; 32] = rand::thread_rng().gen();
+            CookieKeys::new(&secret)
+        }
+    };
+
+    CookiePolicy::new(keys).signed("sid")
+}
+
+// Access log de la app en el formato configurado. Con un archivo se rota cada 10 MiB; sin archivo se escribe en stdout.
+pub fn access_log(config: &LogConfig) -> AccessLog {
+    let format = LogFormat::parse(&config.access_log_format).unwrap_or(LogFormat::Combined);
+    if let Some(ref path) = config.access_log {
+        match RotatingFile::new(path, 10 * 1024 * 1024, 5) {
+            Ok(file) => return AccessLog::new(format, file),
+            Err(e) => error!("Could not open access log '{}': {}", path, e),
+        }
+    }
+    AccessLog::new(format, StdoutSink)
+}
+
+// CORS para el frontend con los orígenes de app.cors_origins
+// ("https://app.example.com", "https://*.example.com"); si no hay ninguno no se permite ningún origen.
+pub fn cors() -> Cors {
+    let cors = Cors::new()
+        .allow_headers(&["Content-Type", "Authorization", "X-API-Key"])
+        .expose_headers(&["RateLimit-Limit", "RateLimit-Remaining", "RateLimit-Reset", "Retry-After"])
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(600));
+
+    settings().cors_origins.iter().fold(cors, |cors, origin| cors.allow_origin(origin))
+}
+
+// Middleware de sesiones de la app. Con app.session_dir las sesiones se guardan en archivos
+// en ese directorio, si no se guardan en memoria.
+pub fn session_middleware() -> SessionMiddleware {
+    if let Some(dir) = settings().session_dir {
+        match FileStore::new(&dir) {
+            Ok(store) => return SessionMiddleware::new(store),
+            Err(e) => error!("Could not use session directory '{}': {}", dir, e),
+        }
+    }
+    SessionMiddleware::new(MemoryStore::new())
+}
+
+// Obtiene un campo de texto de un body JSON
+fn json_field(req: &Request, field: &str) -> Result<String, Response> {
+    match req.body {
+        Some(Body::Json(ref json_value)) => match json_value.get(field).and_then(|value| value.as_str()) {
+            Some(value) => Ok(value.to_string()),
+            None => Err(create_response(400, Some(format!("Missing '{}' in JSON body", field)), None::<HashMap<String, String>>)),
+        },
+        _ => Err(create_response(400, Some("Expected a JSON body".to_string()), None::<HashMap<String, String>>)),
+    }
+}
+
+// Response con un body JSON
+fn json_response(status_code: u16, body: serde_json::Value) -> Response {
+    let mut response = create_response(status_code, Some(body.to_string()), None::<HashMap<String, String>>);
+    response.headers.insert("Content-Type".to_string(), "application/json".to_string());
+    response
+}
+
+// Middleware que acepta las API keys de los servicios en el header X-API-Key
+pub fn api_key_auth() -> ApiKeyAuth {
+    ApiKeyAuth::new(api_keys::identify)
+}
+
+/// Middleware que completa la identidad del request con el usuario de la sesión
+/// y con su rol actual (así un cambio de rol aplica aunque el token se haya emitido antes).
+pub struct UserIdentity;
+
+impl Middleware for UserIdentity {
+    fn handle(&self, mut req: Request, next: Next) -> Response {
+        let username = match req.identity {
+            Some(ref identity) => Some(identity.subject.clone()),
+            None => req.session().get("username"),
+        };
+        if let Some(username) = username {
+            if let Some(role) = users::role(&username) {
+                req.identity = Some(Identity { subject: username, roles: vec![role.as_str().to_string()] });
+            }
+        }
+        next.run(req)
+    }
+}
+
+// Usuario que hace el request (del token Bearer o de la sesión)
+fn current_username(req: &Request) -> Option<String> {
+    req.identity.as_ref().map(|identity| identity.subject.clone())
+}
+
+// Verifica que el usuario pueda modificar un mensaje: su autor, un admin o (si se permite) un moderador.
+// 401 si no hay usuario autenticado, 404 si el mensaje no existe y 403 si no tiene permiso.
+fn authorize_message_change(req: &Request, id: u32, allow_moderators: bool) -> Result<(), Response> {
+    let identity = match req.identity {
+        Some(ref identity) => identity,
+        None => return Err(create_response(401, Some("Not logged in".to_string()), None::<HashMap<String, String>>)),
     };
-    let mut cookies:HashMap<String,String> = HashMap::new();
-    cookies.insert(String::from("username"), username.clone());
+    let message = match get_message(id) {
+        Some(message) => message,
+        None => return Err(create_response(404, Some(format!("Message with ID {} not found", id)), None::<HashMap<String, String>>)),
+    };
+    let allowed = message.username == identity.subject
+        || identity.has_role(Role::Admin.as_str())
+        || (allow_moderators && identity.has_role(Role::Moderator.as_str()));
+    if !allowed {
+        return Err(create_response(403, Some("Only the author can modify this message".to_string()), None::<HashMap<String, String>>));
+    }
+    Ok(())
+}
+
+// Convierte un error de autenticación en un response
+fn auth_error_response(error: AuthError) -> Response {
+    match error {
+        AuthError::InvalidCredentials => create_response(401, Some("Invalid username or password".to_string()), None::<HashMap<String, String>>),
+        AuthError::Locked(remaining) => {
+            let mut response = create_response(429, Some("Account locked after too many failed attempts".to_string()), None::<HashMap<String, String>>);
+            response.headers.insert("Retry-After".to_string(), remaining.as_secs().max(1).to_string());
+            response
+        }
+        AuthError::InvalidPassword(msg) => create_response(400, Some(msg), None::<HashMap<String, String>>),
+    }
+}
 
+// Controller para registrar un usuario
+pub fn register_controller(req: Request) -> Response {
+    let (username, password) = match (json_field(&req, "username"), json_field(&req, "password")) {
+        (Ok(username), Ok(password)) => (username, password),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
 
-    println!("User logged in: {}", username);
-    create_response(200, Some(format!("Welcome, {}!", username)), Some(cookies))
+    match users::register(&username, &password) { // Llamada a register()
+        Ok(_) => {
+            info!("User registered: {}", username);
+            create_response(201, Some(format!("User {} registered", username)), None::<HashMap<String, String>>)
+        }
+        Err(RegisterError::UsernameTaken) => create_response(409, Some("Username already taken".to_string()), None::<HashMap<String, String>>),
+        Err(RegisterError::Invalid(msg)) => create_response(400, Some(msg), None::<HashMap<String, String>>),
+    }
+}
+
+// Controller para el login
+pub fn login_controller(req: Request) -> Response {
+    let (username, password) = match (json_field(&req, "username"), json_field(&req, "password")) {
+        (Ok(username), Ok(password)) => (username, password),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+
+    let username = match users::authenticate(&username, &password) { // Llamada a authenticate()
+        Ok(username) => username,
+        Err(error) => return auth_error_response(error),
+    };
+
+    // Con {"token": true} se emiten tokens Bearer en vez de iniciar una sesión
+    let wants_token = match req.body {
+        Some(Body::Json(ref json_value)) => json_value.get("token").and_then(|v| v.as_bool()).unwrap_or(false),
+        _ => false,
+    };
+    if wants_token {
+        info!("User logged in with token: {}", username);
+        return match tokens::issue(&username) {
+            Ok(body) => json_response(200, body),
+            Err(e) => create_response(500, Some(e), None::<HashMap<String, String>>),
+        };
+    }
+
+    // Se rota el id de la sesión al iniciar sesión para evitar session fixation
+    req.session().rotate();
+    req.session().insert("username", &username);
+
+    info!("User logged in: {}", username);
+    create_response(200, Some(format!("Welcome, {}!", username)), None::<HashMap<String, String>>)
+}
+
+// Controller para cambiar la contraseña del usuario autenticado
+pub fn change_password_controller(req: Request) -> Response {
+    let username = match current_username(&req) {
+        Some(name) => name,
+        None => return create_response(401, Some("Not logged in".to_string()), None::<HashMap<String, String>>),
+    };
+    let (old_password, new_password) = match (json_field(&req, "old_password"), json_field(&req, "new_password")) {
+        (Ok(old_password), Ok(new_password)) => (old_password, new_password),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+
+    match users::change_password(&username, &old_password, &new_password) { // Llamada a change_password()
+        Ok(_) => {
+            // La sesión se rota y los refresh tokens se revocan para que lo robado antes del cambio deje de servir
+            req.session().rotate();
+            tokens::revoke_all(&username);
+            create_response(200, Some("Password changed".to_string()), None::<HashMap<String, String>>)
+        }
+        Err(error) => auth_error_response(error),
+    }
+}
+
+// Controller para cambiar un refresh token por tokens nuevos
+pub fn refresh_token_controller(req: Request) -> Response {
+    let refresh_token = match json_field(&req, "refresh_token") {
+        Ok(refresh_token) => refresh_token,
+        Err(response) => return response,
+    };
+
+    match tokens::refresh(&refresh_token) { // Llamada a refresh()
+        Some(Ok(body)) => json_response(200, body),
+        Some(Err(e)) => create_response(500, Some(e), None::<HashMap<String, String>>),
+        None => create_response(401, Some("Invalid or expired refresh token".to_string()), None::<HashMap<String, String>>),
+    }
+}
+
+// Controller para el logout: elimina la sesión del servidor y la cookie del cliente,
+// y revoca el refresh token si se envía en el body
+pub fn logout_controller(req: Request) -> Response {
+    if let Ok(refresh_token) = json_field(&req, "refresh_token") {
+        tokens::revoke(&refresh_token);
+    }
+    req.session().destroy();
+    create_response(200, Some("Logged out".to_string()), None::<HashMap<String, String>>)
 }
 
 // Controller para obtener todos los mensajes
 pub fn get_messages_controller(_req: Request) -> Response {
-    let messages = get_messages(); // Llamada a get_messages()
-    let body = messages
-        .iter()
-        .map(|message| format!("{}: {} (by {})", message.id, message.content, message.username))
-        .collect::<Vec<String>>()
-        .join("\n");
+    let body = format_messages(); // Llamada a get_messages()
 
     create_response(200, Some(body), None::<HashMap<String, String>>)
 }
@@ -136,14 +423,14 @@ pub fn post_message_controller(req: Request) -> Response {
         _ => return create_response(400, Some("Invalid request body".to_string()), None::<HashMap<String, String>>),
     };
 
-    let username = match req.cookies.get("username") {
-        Some(name) => name.clone(),
-        None => return create_response(400, Some("Missing username in cookies".to_string()), None::<HashMap<String, String>>),
+    let username = match current_username(&req) {
+        Some(name) => name,
+        None => return create_response(401, Some("Not logged in".to_string()), None::<HashMap<String, String>>),
     };
 
     let id = add_message(content.clone(), username.clone()); // Llamada a add_message()
 
-    println!("New message created with ID: {} by user: {}", id, username);
+    info!("New message created with ID: {} by user: {}", id, username);
     create_response(201, Some(format!("Message created with ID: {} by user: {}", id, username)), None::<HashMap<String, String>>)
 }
 
@@ -152,7 +439,11 @@ pub fn edit_existing_message_controller(req: Request) -> Response {
     let id: u32 = req.params.get("id")
                             .and_then(|id_str| id_str.parse::<u32>().ok()) // Intenta parsear el id
                             .unwrap_or(0); // Saca el id de los params, si no hay es 0
-    
+
+    if let Err(response) = authorize_message_change(&req, id, false) {
+        return response;
+    }
+
     let new_message = match req.body {
         Some(Body::Text(ref text)) => {
             text.clone()
@@ -178,17 +469,17 @@ pub fn edit_or_create_message_controller(req: Request) -> Response {
                             .and_then(|id_str| id_str.parse::<u32>().ok()) // Intenta parsear el id
                             .unwrap_or(0); // Saca el id de los params, si no hay es 0
 
-    let messages = MESSAGES.read().unwrap(); // Bloquea para lectura
+    let messages = recover(MESSAGES.read()); // Bloquea para lectura
 
     if id == 0 { // Error si id = 0
         return create_response(404, Some("Message not found".to_string()), None::<HashMap<String, String>>); // Respuesta 404 si id es 0
     }
     if let Some(_message) = messages.get(&id) { // Busca el mensaje si existe, solo lectura
         std::mem::drop(messages);
-        return edit_existing_message_controller(req); // Llama al controlador para editar el mensaje
+        edit_existing_message_controller(req) // Llama al controlador para editar el mensaje
     } else {
         std::mem::drop(messages);
-        return post_message_controller(req); // Crea un nuevo mensaje si no existe
+        post_message_controller(req) // Crea un nuevo mensaje si no existe
     }
 }
 
@@ -199,8 +490,163 @@ pub fn delete_message_by_id_controller(req: Request) -> Response {
                             .and_then(|id_str| id_str.parse::<u32>().ok()) // Intenta parsear el id
                             .unwrap_or(0); // Saca el id de los params, si no hay es 0
 
+    if let Err(response) = authorize_message_change(&req, id, true) {
+        return response;
+    }
+
     match delete_message(id) { // Llamada a delete_message()
         Ok(success_msg) => create_response(200, Some(success_msg), None::<HashMap<String, String>>),
         Err(err_msg) => create_response(404, Some(err_msg), None::<HashMap<String, String>>),
     }
-}
\ No newline at end of file
+}
+
+// Controller de admin para listar los usuarios con su rol
+pub fn list_users_controller(_req: Request) -> Response {
+    let list: Vec<serde_json::Value> = users::list()
+        .iter()
+        .map(|(username, role)| serde_json::json!({ "username": username, "role": role.as_str() }))
+        .collect();
+    json_response(200, serde_json::Value::Array(list))
+}
+
+// Controller de admin para cambiar el rol de un usuario (PATCH /admin/users?username=...)
+pub fn change_role_controller(req: Request) -> Response {
+    let username = match req.params.get("username") {
+        Some(username) => username.clone(),
+        None => return create_response(400, Some("Missing 'username' param".to_string()), None::<HashMap<String, String>>),
+    };
+    let role = match json_field(&req, "role") {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    let role = match Role::parse(&role) {
+        Some(role) => role,
+        None => return create_response(400, Some("Role must be one of: admin, moderator, member, read-only".to_string()), None::<HashMap<String, String>>),
+    };
+
+    match users::set_role(&username, role) { // Llamada a set_role()
+        Ok(_) => {
+            info!("Role of {} changed to {}", username, role.as_str());
+            create_response(200, Some(format!("Role of {} changed to {}", username, role.as_str())), None::<HashMap<String, String>>)
+        }
+        Err(RoleError::UserNotFound) => create_response(404, Some("User not found".to_string()), None::<HashMap<String, String>>),
+        Err(RoleError::LastAdmin) => create_response(409, Some("Cannot remove the last admin".to_string()), None::<HashMap<String, String>>),
+    }
+}
+
+// Datos de una API key en JSON (sin la llave)
+fn api_key_json(info: &ApiKeyInfo) -> serde_json::Value {
+    serde_json::json!({
+        "id": info.id,
+        "name": info.name,
+        "scope": info.scope.as_str(),
+        "created": info.created,
+        "last_used": info.last_used,
+    })
+}
+
+// Controller de admin para crear una API key: {"name": ..., "scope": "read" | "write"}.
+// La llave solo se devuelve en esta respuesta.
+pub fn create_api_key_controller(req: Request) -> Response {
+    let name = match json_field(&req, "name") {
+        Ok(name) => name,
+        Err(response) => return response,
+    };
+    let scope = match json_field(&req, "scope") {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    let scope = match Scope::parse(&scope) {
+        Some(scope) => scope,
+        None => return create_response(400, Some("Scope must be 'read' or 'write'".to_string()), None::<HashMap<String, String>>),
+    };
+
+    match api_keys::create(&name, scope) { // Llamada a create()
+        Ok((info, key)) => {
+            info!("API key {} created for service {}", info.id, info.name);
+            let mut body = api_key_json(&info);
+            body["key"] = serde_json::Value::String(key);
+            json_response(201, body)
+        }
+        Err(message) => create_response(400, Some(message), None::<HashMap<String, String>>),
+    }
+}
+
+// Controller de admin para listar las API keys
+pub fn list_api_keys_controller(_req: Request) -> Response {
+    let list: Vec<serde_json::Value> = api_keys::list().iter().map(api_key_json).collect();
+    json_response(200, serde_json::Value::Array(list))
+}
+
+// Controller de admin para revocar una API key (DELETE /admin/api-keys?id=...)
+pub fn revoke_api_key_controller(req: Request) -> Response {
+    let id: u32 = req.params.get("id")
+                            .and_then(|id_str| id_str.parse::<u32>().ok()) // Intenta parsear el id
+                            .unwrap_or(0); // Saca el id de los params, si no hay es 0
+
+    if api_keys::revoke(id) { // Llamada a revoke()
+        info!("API key {} revoked", id);
+        create_response(200, Some(format!("API key {} revoked", id)), None::<HashMap<String, String>>)
+    } else {
+        create_response(404, Some(format!("API key {} not found", id)), None::<HashMap<String, String>>)
+    }
+}
+
+// Formatea la lista de mensajes como texto
+fn format_messages() -> String {
+    get_messages()
+        .iter()
+        .map(|message| format!("{}: {} (by {})", message.id, message.content, message.username))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Handler WebSocket de mensajes: envía la lista al conectarse y cada vez que el cliente la pide
+pub fn messages_socket_controller(_req: Request, mut socket: WebSocket) {
+    if socket.send(SocketMessage::Text(format_messages())).is_err() {
+        return;
+    }
+
+    loop {
+        match socket.recv() {
+            Ok(SocketMessage::Text(_)) => {
+                if socket.send(SocketMessage::Text(format_messages())).is_err() {
+                    break;
+                }
+            }
+            Ok(SocketMessage::Binary(_)) => {
+                let _ = socket.close(CLOSE_UNSUPPORTED_DATA, "Binary messages not supported");
+                break;
+            }
+            Ok(SocketMessage::Close(_)) | Err(_) => break,
+            Ok(_) => {} // Ping y pong se manejan en recv()
+        }
+    }
+}
+
+// Stream de eventos de los mensajes.
+// Con Last-Event-ID se reenvían los eventos perdidos; si ya no están en el buffer se envía un evento
+// "reset" para que el cliente vuelva a pedir GET /msg.
+pub fn message_events_controller(req: Request, mut stream: EventStream) {
+    let mut last_id = req.header("Last-Event-ID")
+                         .and_then(|id_str| id_str.trim().parse::<u64>().ok())
+                         .unwrap_or_else(|| MESSAGE_EVENTS.last_id()); // Sin Last-Event-ID solo se envían eventos nuevos
+
+    loop {
+        let sent = match MESSAGE_EVENTS.wait_after(last_id, EVENTS_KEEP_ALIVE) {
+            Some(ref events) if events.is_empty() => stream.comment("keep-alive"),
+            Some(events) => events.iter().try_for_each(|event| {
+                last_id = event.id;
+                stream.send(event)
+            }),
+            None => {
+                last_id = MESSAGE_EVENTS.last_id();
+                stream.send(&Event { id: last_id, event: "reset".to_string(), data: String::new() })
+            }
+        };
+
+        if sent.is_err() { // El cliente se desconectó
+            break;
+        }
+    }
+}
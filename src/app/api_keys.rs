@@ -0,0 +1,116 @@
+// Imports
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU32, Ordering};
+extern crate rand;
+extern crate sha2;
+use self::rand::Rng;
+use self::sha2::{Digest, Sha256};
+use app::lazy_static::lazy_static;
+
+use crate::app::users::Role;
+use crate::http::auth::Identity;
+use crate::http::jwt::unix_now;
+
+// Prefijo de las llaves, para reconocerlas (por ejemplo en logs o escáneres de secretos)
+const KEY_PREFIX: &str = "hrk_";
+
+/// Permisos de una API key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Scope> {
+        match scope {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            _ => None,
+        }
+    }
+
+    // Rol con el que actúa un servicio con este scope
+    fn role(&self) -> Role {
+        match self {
+            Scope::Read => Role::ReadOnly,
+            Scope::Write => Role::Member,
+        }
+    }
+}
+
+/// Datos públicos de una API key (la llave en sí solo se conoce al crearla)
+#[derive(Debug, Clone)]
+pub struct ApiKeyInfo {
+    pub id: u32,
+    pub name: String,
+    pub scope: Scope,
+    pub created: u64,
+    pub last_used: Option<u64>,
+}
+
+// API keys activas, guardadas por el hash SHA-256 de la llave
+lazy_static! {
+    static ref API_KEYS: RwLock<HashMap<String, ApiKeyInfo>> = RwLock::new(HashMap::new());
+}
+
+lazy_static! {
+    static ref NEXT_KEY_ID: AtomicU32 = AtomicU32::new(1);
+}
+
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Crea una API key para un servicio. Devuelve sus datos y la llave, que no se vuelve a mostrar.
+pub fn create(name: &str, scope: Scope) -> Result<(ApiKeyInfo, String), String> {
+    if name.is_empty() || name.len() > 64 || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-' || b == b'.') {
+        return Err("Name must be 1-64 characters long and contain only letters, digits, '_', '-' or '.'".to_string());
+    }
+
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    let key = format!("{}{}", KEY_PREFIX, bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let info = ApiKeyInfo {
+        id: NEXT_KEY_ID.fetch_add(1, Ordering::SeqCst),
+        name: name.to_string(),
+        scope,
+        created: unix_now(),
+        last_used: None,
+    };
+
+    let mut keys = API_KEYS.write().unwrap(); // Bloquea para escritura
+    keys.insert(hash_key(&key), info.clone());
+    Ok((info, key))
+}
+
+// Lista de API keys activas, ordenada por id
+pub fn list() -> Vec<ApiKeyInfo> {
+    let keys = API_KEYS.read().unwrap(); // Bloquea para lectura
+    let mut list: Vec<ApiKeyInfo> = keys.values().cloned().collect();
+    list.sort_by_key(|info| info.id);
+    list
+}
+
+// Revoca una API key por id. Devuelve false si no existe.
+pub fn revoke(id: u32) -> bool {
+    let mut keys = API_KEYS.write().unwrap(); // Bloquea para escritura
+    let before = keys.len();
+    keys.retain(|_, info| info.id != id);
+    keys.len() != before
+}
+
+// Identidad de servicio ("service:<name>") de una API key válida
+pub fn identify(key: &str) -> Option<Identity> {
+    let mut keys = API_KEYS.write().unwrap(); // Bloquea para escritura (actualiza last_used)
+    let info = keys.get_mut(&hash_key(key))?;
+    info.last_used = Some(unix_now());
+    Some(Identity { subject: format!("service:{}", info.name), roles: vec![info.scope.role().as_str().to_string()] })
+}
@@ -0,0 +1,106 @@
+// Imports
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+extern crate rand;
+extern crate sha2;
+extern crate tracing;
+use self::rand::Rng;
+use self::sha2::{Digest, Sha256};
+use self::tracing::{error, warn};
+use app::lazy_static::lazy_static;
+
+use crate::app::users;
+use crate::http::auth::BearerAuth;
+use crate::http::jwt::{unix_now, Claims, Jwt};
+
+// Duración de los access tokens (JWT)
+const ACCESS_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+// Duración de los refresh tokens
+const REFRESH_TOKEN_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+struct RefreshToken {
+    username: String,
+    expires: Instant,
+}
+
+// Llave de los JWT. Con app.jwt_rsa_key (ruta a una llave privada PKCS#8 PEM) se usa RS256,
+// si no HS256 con app.jwt_secret (o un secreto aleatorio si no está definido).
+lazy_static! {
+    static ref JWT: Arc<Jwt> = Arc::new(load_jwt());
+}
+
+// Refresh tokens activos, guardados por el hash SHA-256 del token
+lazy_static! {
+    static ref REFRESH_TOKENS: Mutex<HashMap<String, RefreshToken>> = Mutex::new(HashMap::new());
+}
+
+fn load_jwt() -> Jwt {
+    let settings = super::settings();
+    if let Some(path) = settings.jwt_rsa_key {
+        match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|pem| Jwt::rs256_private_pem(&pem)) {
+            Ok(jwt) => return jwt,
+            Err(e) => error!("Could not load JWT RSA key '{}': {}", path, e),
+        }
+    }
+    match settings.jwt_secret {
+        Some(secret) => Jwt::hs256(secret.as_bytes()),
+        None => {
+            warn!("HTTPRUST_JWT_SECRET not set, using a random secret");
+            let secret: [u8; 32] = rand::thread_rng().gen();
+            Jwt::hs256(&secret)
+        }
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Middleware que valida los access tokens emitidos por la app
+pub fn bearer_auth() -> BearerAuth {
+    BearerAuth::new(Arc::clone(&JWT))
+}
+
+// Emite un access token y un refresh token para un usuario
+pub fn issue(username: &str) -> Result<serde_json::Value, String> {
+    let now = unix_now();
+    let roles = users::role(username).map(|role| vec![role.as_str().to_string()]).unwrap_or_default();
+    let claims = Claims { sub: username.to_string(), iat: now, exp: now + ACCESS_TOKEN_TTL.as_secs(), roles };
+    let access_token = JWT.encode(&claims)?;
+
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    let refresh_token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut tokens = REFRESH_TOKENS.lock().unwrap();
+    let now = Instant::now();
+    tokens.retain(|_, token| token.expires > now); // Limpia los expirados
+    tokens.insert(hash_token(&refresh_token), RefreshToken { username: username.to_string(), expires: now + REFRESH_TOKEN_TTL });
+
+    Ok(serde_json::json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": ACCESS_TOKEN_TTL.as_secs(),
+        "refresh_token": refresh_token,
+    }))
+}
+
+// Cambia un refresh token por tokens nuevos. El refresh token usado queda invalidado.
+pub fn refresh(refresh_token: &str) -> Option<Result<serde_json::Value, String>> {
+    let stored = REFRESH_TOKENS.lock().unwrap().remove(&hash_token(refresh_token))?;
+    if stored.expires <= Instant::now() {
+        return None;
+    }
+    Some(issue(&stored.username))
+}
+
+// Invalida un refresh token (logout)
+pub fn revoke(refresh_token: &str) {
+    REFRESH_TOKENS.lock().unwrap().remove(&hash_token(refresh_token));
+}
+
+// Invalida todos los refresh tokens de un usuario (al cambiar la contraseña)
+pub fn revoke_all(username: &str) {
+    REFRESH_TOKENS.lock().unwrap().retain(|_, token| !token.username.eq_ignore_ascii_case(username));
+}
@@ -0,0 +1,254 @@
+// Imports
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+extern crate base64;
+extern crate pbkdf2;
+extern crate rand;
+extern crate sha2;
+extern crate tracing;
+use self::base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use self::rand::Rng;
+use self::sha2::Sha256;
+use self::tracing::warn;
+use app::lazy_static::lazy_static;
+
+// Iteraciones de PBKDF2-HMAC-SHA256 (recomendación de OWASP)
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// Intentos fallidos permitidos antes de bloquear la cuenta
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+// Tiempo que la cuenta queda bloqueada (por defecto, ver set_lockout_duration)
+const DEFAULT_LOCKOUT_DURATION: Duration = Duration::from_secs(15 * 60);
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+struct User {
+    username: String,
+    password_hash: String,
+    role: Role,
+    failed_attempts: u32,
+    locked_until: Option<Instant>,
+}
+
+/// Roles de los usuarios
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Admin,
+    Moderator,
+    Member,
+    ReadOnly,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+            Role::Member => "member",
+            Role::ReadOnly => "read-only",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "admin" => Some(Role::Admin),
+            "moderator" => Some(Role::Moderator),
+            "member" => Some(Role::Member),
+            "read-only" => Some(Role::ReadOnly),
+            _ => None,
+        }
+    }
+}
+
+/// Errores al cambiar el rol de un usuario
+#[derive(Debug)]
+pub enum RoleError {
+    UserNotFound,
+    LastAdmin,
+}
+
+/// Errores al autenticar un usuario
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidCredentials,
+    Locked(Duration),
+    InvalidPassword(String),
+}
+
+/// Errores al registrar un usuario
+#[derive(Debug)]
+pub enum RegisterError {
+    UsernameTaken,
+    Invalid(String),
+}
+
+// Usuarios registrados, la llave es el username en minúsculas para detectar duplicados
+lazy_static! {
+    static ref USERS: RwLock<HashMap<String, User>> = RwLock::new(HashMap::new());
+    static ref LOCKOUT_DURATION: RwLock<Duration> = RwLock::new(DEFAULT_LOCKOUT_DURATION);
+}
+
+// Cambia el tiempo que una cuenta queda bloqueada después de MAX_FAILED_ATTEMPTS intentos fallidos
+pub fn set_lockout_duration(duration: Duration) {
+    *LOCKOUT_DURATION.write().unwrap() = duration;
+}
+
+// Hash de una contraseña con sal aleatoria: "pbkdf2-sha256$iteraciones$sal$hash"
+fn hash_password(password: &str) -> String {
+    let salt: [u8; 16] = rand::thread_rng().gen();
+    let hash = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), &salt, PBKDF2_ITERATIONS);
+    format!(
+        "pbkdf2-sha256${}${}${}",
+        PBKDF2_ITERATIONS,
+        STANDARD_NO_PAD.encode(salt),
+        STANDARD_NO_PAD.encode(hash)
+    )
+}
+
+// Verifica una contraseña contra un hash guardado (comparación en tiempo constante)
+fn verify_password(password: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    if parts.len() != 4 || parts[0] != "pbkdf2-sha256" {
+        return false;
+    }
+    let iterations = match parts[1].parse::<u32>() {
+        Ok(iterations) => iterations,
+        Err(_) => return false,
+    };
+    let (salt, expected) = match (STANDARD_NO_PAD.decode(parts[2]), STANDARD_NO_PAD.decode(parts[3])) {
+        (Ok(salt), Ok(expected)) => (salt, expected),
+        _ => return false,
+    };
+
+    let hash = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), &salt, iterations);
+    expected.len() == hash.len() && expected.iter().zip(hash.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("Password must be at least {} characters long", MIN_PASSWORD_LENGTH));
+    }
+    Ok(())
+}
+
+// Registra un usuario nuevo. Siempre queda como miembro, los roles solo los cambia un admin con set_role().
+pub fn register(username: &str, password: &str) -> Result<(), RegisterError> {
+    create(username, password, Role::Member)
+}
+
+// Crea una cuenta de admin (al iniciar el servidor, con las credenciales configuradas)
+pub fn create_admin(username: &str, password: &str) -> Result<(), RegisterError> {
+    create(username, password, Role::Admin)
+}
+
+fn create(username: &str, password: &str, role: Role) -> Result<(), RegisterError> {
+    if username.len() < 3 || username.len() > 32 || !username.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-' || b == b'.') {
+        return Err(RegisterError::Invalid("Username must be 3-32 characters long and contain only letters, digits, '_', '-' or '.'".to_string()));
+    }
+    validate_password(password).map_err(RegisterError::Invalid)?;
+
+    // El hash se calcula antes de tomar el lock porque es lento a propósito
+    let password_hash = hash_password(password);
+
+    let mut users = USERS.write().unwrap(); // Bloquea para escritura
+    let key = username.to_lowercase();
+    if users.contains_key(&key) {
+        return Err(RegisterError::UsernameTaken);
+    }
+    users.insert(key, User { username: username.to_string(), password_hash, role, failed_attempts: 0, locked_until: None });
+    Ok(())
+}
+
+// Rol de un usuario (None si no existe)
+pub fn role(username: &str) -> Option<Role> {
+    let users = USERS.read().unwrap(); // Bloquea para lectura
+    users.get(&username.to_lowercase()).map(|user| user.role)
+}
+
+// Lista de usuarios con su rol, ordenada por username
+pub fn list() -> Vec<(String, Role)> {
+    let users = USERS.read().unwrap(); // Bloquea para lectura
+    let mut list: Vec<(String, Role)> = users.values().map(|user| (user.username.clone(), user.role)).collect();
+    list.sort_by(|a, b| a.0.cmp(&b.0));
+    list
+}
+
+// Cambia el rol de un usuario. No se permite quitar el rol al último admin.
+pub fn set_role(username: &str, role: Role) -> Result<(), RoleError> {
+    let mut users = USERS.write().unwrap(); // Bloquea para escritura
+    let admins = users.values().filter(|user| user.role == Role::Admin).count();
+    let user = users.get_mut(&username.to_lowercase()).ok_or(RoleError::UserNotFound)?;
+    if user.role == Role::Admin && role != Role::Admin && admins == 1 {
+        return Err(RoleError::LastAdmin);
+    }
+    user.role = role;
+    Ok(())
+}
+
+// Autentica un usuario, devuelve el username tal como se registró.
+// Después de MAX_FAILED_ATTEMPTS intentos fallidos la cuenta se bloquea por LOCKOUT_DURATION.
+pub fn authenticate(username: &str, password: &str) -> Result<String, AuthError> {
+    let key = username.to_lowercase();
+
+    let stored = {
+        let users = USERS.read().unwrap(); // Bloquea para lectura
+        users.get(&key).map(|user| user.password_hash.clone())
+    };
+
+    let stored = match stored {
+        Some(stored) => stored,
+        None => {
+            // Se calcula un hash igual para no revelar por tiempo de respuesta si el usuario existe
+            hash_password(password);
+            return Err(AuthError::InvalidCredentials);
+        }
+    };
+
+    // La contraseña se verifica aunque la cuenta esté bloqueada, por la misma razón
+    let valid = verify_password(password, &stored);
+
+    let mut users = USERS.write().unwrap(); // Bloquea para escritura
+    let user = match users.get_mut(&key) {
+        Some(user) => user,
+        None => return Err(AuthError::InvalidCredentials),
+    };
+    if let Some(locked_until) = user.locked_until {
+        let now = Instant::now();
+        if locked_until > now {
+            // Sin la contraseña correcta se responde como a un usuario que no existe, el bloqueo solo
+            // se informa a quien ya demostró conocerla
+            if !valid {
+                return Err(AuthError::InvalidCredentials);
+            }
+            return Err(AuthError::Locked(locked_until - now));
+        }
+    }
+    if valid {
+        user.failed_attempts = 0;
+        user.locked_until = None;
+        Ok(user.username.clone())
+    } else {
+        user.failed_attempts += 1;
+        if user.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            user.failed_attempts = 0;
+            user.locked_until = Some(Instant::now() + *LOCKOUT_DURATION.read().unwrap());
+            warn!("Account '{}' locked after too many failed logins", user.username);
+        }
+        Err(AuthError::InvalidCredentials)
+    }
+}
+
+// Cambia la contraseña de un usuario verificando la contraseña actual
+pub fn change_password(username: &str, old_password: &str, new_password: &str) -> Result<(), AuthError> {
+    validate_password(new_password).map_err(AuthError::InvalidPassword)?;
+    authenticate(username, old_password)?;
+
+    let password_hash = hash_password(new_password);
+    let mut users = USERS.write().unwrap(); // Bloquea para escritura
+    match users.get_mut(&username.to_lowercase()) {
+        Some(user) => {
+            user.password_hash = password_hash;
+            Ok(())
+        }
+        None => Err(AuthError::InvalidCredentials),
+    }
+}
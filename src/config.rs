@@ -0,0 +1,383 @@
+// Imports
+use std::{env, fmt, fs, net::{IpAddr, SocketAddr}, path::Path};
+extern crate serde;
+extern crate toml;
+extern crate tracing_subscriber;
+use self::serde::Deserialize;
+use self::tracing_subscriber::EnvFilter;
+
+use crate::http::access_log::LogFormat;
+use crate::http::pool::Overload;
+
+// Archivo de configuración que se usa si existe y no se indica otro
+const DEFAULT_CONFIG_FILE: &str = "httprust.toml";
+
+/// Configuración completa. Cada valor se toma, de menor a mayor prioridad, de:
+/// los defaults, el archivo TOML, las variables HTTPRUST_* y los flags de la línea de comandos.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub log: LogConfig,
+    pub app: AppConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    // Una o más IPs separadas por comas, ej: "127.0.0.1,::1"
+    pub bind: String,
+    pub port: u16,
+    // Máximo de workers; el pool arranca con pool_min_size (por defecto igual, tamaño fijo) y crece hasta pool_size
+    pub pool_size: usize,
+    pub pool_min_size: Option<usize>,
+    // Segundos sin trabajo después de los cuales terminan los workers que sobran del mínimo
+    pub pool_keep_alive_secs: u64,
+    pub worker_stack_bytes: Option<usize>,
+    // Conexiones que pueden esperar un worker y qué hacer cuando se llena la cola (block, reject o drop-oldest)
+    pub queue_capacity: usize,
+    pub overload_policy: String,
+    pub max_body_bytes: usize,
+    pub read_timeout_secs: u64,
+    pub write_timeout_secs: u64,
+    pub shutdown_drain_secs: u64,
+    // Si se indica, el servidor escucha en este Unix socket en vez de TCP (sin IP de cliente, los límites
+    // por IP se comparten entre todos)
+    pub unix_socket: Option<String>,
+    // Permisos del Unix socket en octal, ej: "660"
+    pub unix_socket_mode: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            bind: "0.0.0.0".to_string(),
+            port: 8080,
+            pool_size: 10,
+            pool_min_size: None,
+            pool_keep_alive_secs: 60,
+            worker_stack_bytes: None,
+            queue_capacity: 1024,
+            overload_policy: "reject".to_string(),
+            max_body_bytes: 1024 * 1024,
+            read_timeout_secs: 30,
+            write_timeout_secs: 30,
+            shutdown_drain_secs: 5,
+            unix_socket: None,
+            unix_socket_mode: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    // Direcciones en las que escucha el servidor (las IPs inválidas se reportan en validate)
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        list(&self.bind).iter().filter_map(|ip| ip.parse::<IpAddr>().ok()).map(|ip| SocketAddr::new(ip, self.port)).collect()
+    }
+
+    // Permisos del Unix socket (un modo inválido se reporta en validate)
+    pub fn unix_socket_mode(&self) -> Option<u32> {
+        self.unix_socket_mode.as_ref().and_then(|mode| u32::from_str_radix(mode.trim_start_matches("0o"), 8).ok())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // Filtro de EnvFilter, ej: "info,httprust::http=debug"
+    pub level: String,
+    // Archivo del access log (se rota); sin archivo se escribe en stdout
+    pub access_log: Option<String>,
+    pub access_log_format: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig { level: "info".to_string(), access_log: None, access_log_format: "combined".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    // Secretos de las cookies, el primero firma y los demás solo verifican (rotación)
+    pub cookie_secrets: Vec<String>,
+    pub session_dir: Option<String>,
+    pub jwt_secret: Option<String>,
+    // Llave privada PKCS#8 PEM para firmar los JWT con RS256
+    pub jwt_rsa_key: Option<String>,
+    // Cuentas de admin que se crean al iniciar, "usuario:contraseña"
+    pub admins: Vec<String>,
+    pub cors_origins: Vec<String>,
+}
+
+/// Error al cargar la configuración
+#[derive(Debug)]
+pub enum ConfigError {
+    // Se pidió --help
+    Help,
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Help => write!(f, "{}", usage()),
+            ConfigError::Invalid(errors) => {
+                writeln!(f, "Invalid configuration:")?;
+                // Los errores del TOML ocupan varias líneas (muestran la línea del archivo)
+                for error in errors {
+                    writeln!(f, "  - {}", error.trim_end().replace('\n', "\n    "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Valores configurables por entorno y CLI: (clave, variable de entorno, flag).
+// Los secretos no tienen flag para que no queden a la vista en la lista de procesos.
+const SETTINGS: [(&str, &str, &str); 23] = [
+    ("server.bind", "HTTPRUST_BIND", "--bind"),
+    ("server.port", "HTTPRUST_PORT", "--port"),
+    ("server.pool_size", "HTTPRUST_POOL_SIZE", "--pool-size"),
+    ("server.pool_min_size", "HTTPRUST_POOL_MIN_SIZE", "--pool-min-size"),
+    ("server.pool_keep_alive_secs", "HTTPRUST_POOL_KEEP_ALIVE", "--pool-keep-alive"),
+    ("server.worker_stack_bytes", "HTTPRUST_WORKER_STACK_BYTES", "--worker-stack-bytes"),
+    ("server.queue_capacity", "HTTPRUST_QUEUE_CAPACITY", "--queue-capacity"),
+    ("server.overload_policy", "HTTPRUST_OVERLOAD_POLICY", "--overload-policy"),
+    ("server.max_body_bytes", "HTTPRUST_MAX_BODY_BYTES", "--max-body-bytes"),
+    ("server.read_timeout_secs", "HTTPRUST_READ_TIMEOUT", "--read-timeout"),
+    ("server.write_timeout_secs", "HTTPRUST_WRITE_TIMEOUT", "--write-timeout"),
+    ("server.shutdown_drain_secs", "HTTPRUST_SHUTDOWN_DRAIN", "--shutdown-drain"),
+    ("server.unix_socket", "HTTPRUST_UNIX_SOCKET", "--unix-socket"),
+    ("server.unix_socket_mode", "HTTPRUST_UNIX_SOCKET_MODE", "--unix-socket-mode"),
+    ("log.level", "HTTPRUST_LOG", "--log"),
+    ("log.access_log", "HTTPRUST_ACCESS_LOG", "--access-log"),
+    ("log.access_log_format", "HTTPRUST_ACCESS_LOG_FORMAT", "--access-log-format"),
+    ("app.cookie_secrets", "HTTPRUST_COOKIE_SECRET", ""),
+    ("app.session_dir", "HTTPRUST_SESSION_DIR", "--session-dir"),
+    ("app.jwt_secret", "HTTPRUST_JWT_SECRET", ""),
+    ("app.jwt_rsa_key", "HTTPRUST_JWT_RSA_KEY", "--jwt-rsa-key"),
+    ("app.admins", "HTTPRUST_ADMINS", ""),
+    ("app.cors_origins", "HTTPRUST_CORS_ORIGINS", "--cors-origins"),
+];
+
+// Texto de --help
+pub fn usage() -> String {
+    let mut usage = String::from("Usage: httprust [--config <file>] [-v | -vv | -q] [options]\n\nOptions (also settable in the config file and as environment variables):\n");
+    usage.push_str(&format!("  {:<28} {:<28} {}\n", "--config <file>", "HTTPRUST_CONFIG", "TOML config file"));
+    for (key, env_var, flag) in SETTINGS.iter() {
+        let flag = if flag.is_empty() { "(environment only)".to_string() } else { format!("{} <value>", flag) };
+        usage.push_str(&format!("  {:<28} {:<28} {}\n", flag, env_var, key));
+    }
+    usage
+}
+
+// Lista separada por comas
+fn list(value: &str) -> Vec<String> {
+    value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()
+}
+
+fn optional(value: &str) -> Option<String> {
+    if value.is_empty() { None } else { Some(value.to_string()) }
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse::<T>().map_err(|_| format!("'{}' is not a valid number", value))
+}
+
+impl Config {
+    // Carga la configuración a partir de los argumentos de la línea de comandos (sin el nombre del programa)
+    pub fn load(args: &[String]) -> Result<Config, ConfigError> {
+        let mut errors = Vec::new();
+
+        // Flags de la línea de comandos, se aplican al final para que tengan prioridad
+        let mut config_file = None;
+        let mut cli: Vec<(&str, String)> = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            match flag {
+                "-h" | "--help" => return Err(ConfigError::Help),
+                "-q" | "--quiet" => cli.push(("log.level", "warn".to_string())),
+                "-v" | "--verbose" => cli.push(("log.level", "debug".to_string())),
+                "-vv" => cli.push(("log.level", "trace".to_string())),
+                _ => {
+                    let key = if flag == "--config" {
+                        "config"
+                    } else {
+                        match SETTINGS.iter().find(|(_, _, name)| !name.is_empty() && *name == flag) {
+                            Some((key, _, _)) => key,
+                            None => {
+                                errors.push(format!("Unknown argument '{}'", arg));
+                                continue;
+                            }
+                        }
+                    };
+                    let value = match inline_value.or_else(|| args.next().cloned()) {
+                        Some(value) => value,
+                        None => {
+                            errors.push(format!("Missing value for {}", flag));
+                            continue;
+                        }
+                    };
+                    if key == "config" {
+                        config_file = Some(value);
+                    } else {
+                        cli.push((key, value));
+                    }
+                }
+            }
+        }
+
+        // Archivo: --config, HTTPRUST_CONFIG o httprust.toml si existe
+        let config_file = config_file
+            .or_else(|| env::var("HTTPRUST_CONFIG").ok())
+            .or_else(|| if Path::new(DEFAULT_CONFIG_FILE).exists() { Some(DEFAULT_CONFIG_FILE.to_string()) } else { None });
+        let mut config = match config_file {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(text) => toml::from_str::<Config>(&text).unwrap_or_else(|e| {
+                    errors.push(format!("{}: {}", path, e));
+                    Config::default()
+                }),
+                Err(e) => {
+                    errors.push(format!("Could not read config file '{}': {}", path, e));
+                    Config::default()
+                }
+            },
+            None => Config::default(),
+        };
+
+        // Variables de entorno (RUST_LOG se acepta si no está HTTPRUST_LOG)
+        for (key, env_var, _) in SETTINGS.iter() {
+            let value = match env::var(env_var) {
+                Ok(value) => value,
+                Err(_) if *key == "log.level" => match env::var("RUST_LOG") {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+            if let Err(e) = config.set(key, &value) {
+                errors.push(format!("{}: {}", env_var, e));
+            }
+        }
+
+        for (key, value) in cli {
+            if let Err(e) = config.set(key, &value) {
+                let flag = SETTINGS.iter().find(|(name, _, _)| *name == key).map(|(_, _, flag)| *flag).unwrap_or(key);
+                errors.push(format!("{}: {}", flag, e));
+            }
+        }
+
+        errors.extend(config.validate());
+        if errors.is_empty() { Ok(config) } else { Err(ConfigError::Invalid(errors)) }
+    }
+
+    // Asigna un valor a partir de su clave y su representación en texto
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "server.bind" => self.server.bind = value.trim().to_string(),
+            "server.port" => self.server.port = number(value)?,
+            "server.pool_size" => self.server.pool_size = number(value)?,
+            "server.pool_min_size" => self.server.pool_min_size = if value.is_empty() { None } else { Some(number(value)?) },
+            "server.pool_keep_alive_secs" => self.server.pool_keep_alive_secs = number(value)?,
+            "server.worker_stack_bytes" => self.server.worker_stack_bytes = if value.is_empty() { None } else { Some(number(value)?) },
+            "server.queue_capacity" => self.server.queue_capacity = number(value)?,
+            "server.overload_policy" => self.server.overload_policy = value.trim().to_string(),
+            "server.max_body_bytes" => self.server.max_body_bytes = number(value)?,
+            "server.read_timeout_secs" => self.server.read_timeout_secs = number(value)?,
+            "server.write_timeout_secs" => self.server.write_timeout_secs = number(value)?,
+            "server.shutdown_drain_secs" => self.server.shutdown_drain_secs = number(value)?,
+            "server.unix_socket" => self.server.unix_socket = optional(value),
+            "server.unix_socket_mode" => self.server.unix_socket_mode = optional(value),
+            "log.level" => self.log.level = value.trim().to_string(),
+            "log.access_log" => self.log.access_log = optional(value),
+            "log.access_log_format" => self.log.access_log_format = value.trim().to_string(),
+            "app.cookie_secrets" => self.app.cookie_secrets = list(value),
+            "app.session_dir" => self.app.session_dir = optional(value),
+            "app.jwt_secret" => self.app.jwt_secret = optional(value),
+            "app.jwt_rsa_key" => self.app.jwt_rsa_key = optional(value),
+            "app.admins" => self.app.admins = list(value),
+            "app.cors_origins" => self.app.cors_origins = list(value),
+            _ => return Err(format!("Unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+    // Errores de los valores que no tienen sentido, se reportan todos juntos al iniciar
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for ip in list(&self.server.bind) {
+            if ip.parse::<IpAddr>().is_err() {
+                errors.push(format!("server.bind: '{}' is not an IP address", ip));
+            }
+        }
+        if list(&self.server.bind).is_empty() {
+            errors.push("server.bind: at least one IP address is required".to_string());
+        }
+        if self.server.port == 0 {
+            errors.push("server.port: must be between 1 and 65535".to_string());
+        }
+        if self.server.pool_size == 0 || self.server.pool_size > 1024 {
+            errors.push(format!("server.pool_size: must be between 1 and 1024, got {}", self.server.pool_size));
+        }
+        if self.server.pool_min_size.map(|min| min > self.server.pool_size).unwrap_or(false) {
+            errors.push(format!("server.pool_min_size: must not be greater than server.pool_size ({})", self.server.pool_size));
+        }
+        if self.server.pool_keep_alive_secs == 0 {
+            errors.push("server.pool_keep_alive_secs: must be greater than 0".to_string());
+        }
+        // Menos que esto no alcanza ni para el parser y los controllers
+        if self.server.worker_stack_bytes.map(|bytes| bytes < 64 * 1024).unwrap_or(false) {
+            errors.push("server.worker_stack_bytes: must be at least 65536".to_string());
+        }
+        if self.server.queue_capacity == 0 {
+            errors.push("server.queue_capacity: must be greater than 0".to_string());
+        }
+        if Overload::parse(&self.server.overload_policy).is_none() {
+            errors.push(format!("server.overload_policy: must be block, reject or drop-oldest, got '{}'", self.server.overload_policy));
+        }
+        if self.server.max_body_bytes == 0 {
+            errors.push("server.max_body_bytes: must be greater than 0".to_string());
+        }
+        if self.server.read_timeout_secs == 0 || self.server.write_timeout_secs == 0 {
+            errors.push("server.read_timeout_secs and server.write_timeout_secs: must be greater than 0".to_string());
+        }
+        if let Some(ref mode) = self.server.unix_socket_mode {
+            if self.server.unix_socket_mode().map(|mode| mode > 0o777).unwrap_or(true) {
+                errors.push(format!("server.unix_socket_mode: '{}' is not an octal file mode", mode));
+            }
+        }
+        if self.server.unix_socket.is_some() && cfg!(not(unix)) {
+            errors.push("server.unix_socket: Unix sockets are not supported on this platform".to_string());
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log.level) {
+            errors.push(format!("log.level: invalid filter '{}': {}", self.log.level, e));
+        }
+        if LogFormat::parse(&self.log.access_log_format).is_none() {
+            errors.push(format!("log.access_log_format: must be common, combined or json, got '{}'", self.log.access_log_format));
+        }
+        // Solo se muestra el usuario, el resto puede ser una contraseña
+        for admin in &self.app.admins {
+            match admin.split_once(':') {
+                Some((username, password)) if !username.is_empty() && !password.is_empty() => {}
+                _ => errors.push(format!("app.admins: entries must be 'username:password', got '{}:...'", admin.split(':').next().unwrap_or(""))),
+            }
+        }
+        // La app permite credenciales en CORS, entonces los orígenes se deben listar
+        if self.app.cors_origins.iter().any(|origin| origin == "*") {
+            errors.push("app.cors_origins: '*' cannot be used because the app allows credentials, list the origins".to_string());
+        }
+        if let Some(ref path) = self.app.jwt_rsa_key {
+            if !Path::new(path).is_file() {
+                errors.push(format!("app.jwt_rsa_key: '{}' does not exist", path));
+            }
+        }
+        errors
+    }
+}
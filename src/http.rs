@@ -1,20 +1,204 @@
 // Imports
 use std::{
+    cell::RefCell,
     collections::HashMap,
-    io::{prelude::*, BufReader, Read, Write},
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    io::{self, prelude::*, BufReader, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
+extern crate signal_hook;
+extern crate tracing;
+use self::signal_hook::{consts::{SIGINT, SIGTERM}, iterator::Signals};
+use self::tracing::{debug, error, info, info_span, Span};
 
+pub mod access_log;
+use crate::http::access_log::{AccessEntry, AccessLog};
+pub mod auth;
+use crate::http::auth::RequireRoles;
+pub mod connection;
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use crate::http::connection::UnixSocket;
+use crate::http::connection::{Listener, Stream};
+pub mod jwt;
 pub mod pool;
-use crate::http::pool::ThreadPool;
+use crate::http::pool::{panic_message, Overload, PoolStats, ThreadPool};
+pub mod cors;
+pub mod date;
+pub mod handle;
+use crate::http::handle::{Lifecycle, ServerHandle};
+pub mod health;
+use crate::http::health::{HealthCheck, PoolCheck};
+pub mod metrics;
+use crate::http::metrics::Metrics;
+pub mod middleware;
+use crate::http::middleware::{Middleware, Next};
 pub mod parser;
-use crate::http::parser::{parse_request, create_response, Response};
+use crate::http::parser::{parse_request, create_response, format_response, Request, Response};
+pub mod ratelimit;
 pub mod router;
-use crate::http::router::{Controller, RouterKey};
+use crate::http::router::{Controller, EventStreamHandler, RouterKey, WebSocketHandler};
+pub mod secure_cookies;
+use crate::http::secure_cookies::CookiePolicy;
+pub mod session;
+pub mod sse;
+use crate::http::sse::EventStream;
+pub mod websocket;
+use crate::http::websocket::WebSocket;
 
-// Función para manejar las conexiones
-fn handle_connection(mut stream: TcpStream, controllers: &HashMap<RouterKey, Controller>) {
-    let mut buf_reader = BufReader::new(&mut stream);
+// Segundos que se piden esperar en Retry-After cuando el pool está saturado
+const OVERLOAD_RETRY_AFTER: u64 = 1;
+
+// Máximo por defecto de conexiones con upgrade abiertas al mismo tiempo
+const DEFAULT_MAX_UPGRADES: usize = 1024;
+// Pausa del accept loop cuando no hay conexiones pendientes, antes de volver a mirar si hay que terminar
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+// Conexiones con upgrade abiertas. Cada una ocupa un thread propio, entonces se limitan.
+struct UpgradeSlots {
+    open: AtomicUsize,
+    max: usize,
+}
+
+// Lugar ocupado por una conexión con upgrade, se libera al soltarlo
+struct UpgradeSlot(Arc<UpgradeSlots>);
+
+impl UpgradeSlots {
+    fn new(max: usize) -> UpgradeSlots {
+        UpgradeSlots { open: AtomicUsize::new(0), max }
+    }
+
+    fn acquire(slots: &Arc<UpgradeSlots>) -> Option<UpgradeSlot> {
+        slots
+            .open
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| if open < slots.max { Some(open + 1) } else { None })
+            .ok()
+            .map(|_| UpgradeSlot(Arc::clone(slots)))
+    }
+}
+
+impl Drop for UpgradeSlot {
+    fn drop(&mut self) {
+        self.0.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Handler que recibe una conexión con upgrade
+#[derive(Clone, Copy)]
+enum UpgradeHandler {
+    WebSocket(WebSocketHandler),
+    Events(EventStreamHandler),
+}
+
+impl UpgradeHandler {
+    // Status del response que abre la conexión
+    fn status(&self) -> u16 {
+        match self {
+            UpgradeHandler::WebSocket(_) => 101,
+            UpgradeHandler::Events(_) => 200,
+        }
+    }
+}
+
+// Upgrade aceptado por la cadena de middlewares, la conexión pasa al handler después de enviar el response
+struct Upgrade {
+    handler: UpgradeHandler,
+    request: Request,
+    slot: UpgradeSlot,
+}
+
+impl Upgrade {
+    // Envía el response y corre el handler en su propio thread, la conexión vive más que un request
+    // y no debe ocupar un worker del pool. El lugar reservado se libera cuando termina el handler.
+    fn start(self, response: &Response, reader: BufReader<Stream>, mut writer: Stream, metrics: &Metrics) {
+        let Upgrade { handler, request, slot } = self;
+        let span = Span::current();
+        let spawned = match handler {
+            UpgradeHandler::WebSocket(handler) => {
+                if !write_response(&mut writer, response, metrics) {
+                    return;
+                }
+                // El socket puede quedar inactivo mucho tiempo entre mensajes
+                if let Err(e) = writer.set_read_timeout(None) {
+                    error!("Could not clear WebSocket read timeout: {}", e);
+                }
+                let socket = WebSocket::new(reader, writer);
+                debug!("Upgraded to WebSocket");
+                thread::Builder::new().name("websocket".to_string()).spawn(move || {
+                    let _span = span.entered();
+                    let _slot = slot;
+                    handler(request, socket)
+                })
+            }
+            UpgradeHandler::Events(handler) => {
+                let stream = match EventStream::open(writer, response) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!("Error opening event stream: {}", e);
+                        return;
+                    }
+                };
+                debug!("Opened event stream");
+                thread::Builder::new().name("event-stream".to_string()).spawn(move || {
+                    let _span = span.entered();
+                    let _slot = slot;
+                    handler(request, stream)
+                })
+            }
+        };
+        if let Err(e) = spawned {
+            error!("Could not spawn thread for upgraded connection: {}", e);
+        }
+    }
+}
+
+// Rutas compartidas entre los threads del pool
+struct ServerState {
+    router: HashMap<RouterKey, Controller>,
+    websockets: HashMap<String, WebSocketHandler>,
+    event_streams: HashMap<String, EventStreamHandler>,
+    upgrades: Arc<UpgradeSlots>,
+    cookie_policy: Option<Arc<CookiePolicy>>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    route_middlewares: HashMap<RouterKey, Vec<Arc<dyn Middleware>>>,
+    access_log: Option<Arc<AccessLog>>,
+    metrics: Arc<Metrics>,
+    metrics_path: Option<String>,
+    pool_stats: Arc<PoolStats>,
+    health_paths: Option<(String, String)>,
+    readiness_checks: Vec<(String, Arc<dyn HealthCheck>)>,
+    shutting_down: Arc<AtomicBool>,
+    max_body_size: Option<usize>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+// Función para manejar las conexiones, sirve para cualquier stream (TCP o Unix socket)
+fn handle_connection(stream: Stream, state: &ServerState) {
+    let start = Instant::now();
+    let remote_addr = stream.peer_addr();
+    let _connection = info_span!("connection", peer = %remote_addr.map(|addr| addr.to_string()).unwrap_or_else(|| "local".to_string())).entered();
+    debug!("Connection established");
+
+    // Un cliente lento no puede ocupar un worker indefinidamente
+    if let Err(e) = stream.set_read_timeout(state.read_timeout).and_then(|_| stream.set_write_timeout(state.write_timeout)) {
+        error!("Could not set socket timeouts: {}", e);
+        return;
+    }
+
+    // Se separa el stream en lectura y escritura para poder entregarlo a un WebSocket
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            error!("Could not clone stream: {}", e);
+            return;
+        }
+    };
+    let mut buf_reader = BufReader::new(stream);
     let mut headers = String::new();
 
     // Lectura de headers (línea por línea)
@@ -29,12 +213,18 @@ fn handle_connection(mut stream: TcpStream, controllers: &HashMap<RouterKey, Con
                 headers.push_str(&line);
             }
             Err(e) => {
-                eprintln!("[Error]: Error reading from stream: {}", e);
+                error!("Error reading from stream: {}", e);
                 return;
             }
         }
     }
 
+    // Conexiones que se cierran sin enviar nada (probes de TCP, chequeo de socket en uso) no son un request
+    if headers.is_empty() {
+        debug!("Connection closed without a request");
+        return;
+    }
+
     // Analiza el tamaño del cuerpo si Content-Length está
     let content_length = headers
         .lines()
@@ -47,77 +237,264 @@ fn handle_connection(mut stream: TcpStream, controllers: &HashMap<RouterKey, Con
         })
         .unwrap_or(0);
 
+    // Bodies más grandes que el límite se rechazan sin leerlos
+    if state.max_body_size.map(|max| content_length > max).unwrap_or(false) {
+        let entry = AccessEntry::new(start, remote_addr, headers.lines().next().unwrap_or(""));
+        let response = create_response(413, Some("[Error]: Request body too large".to_string()), None::<HashMap<String, String>>);
+        write_response(&mut writer, &response, &state.metrics);
+        record(state, &entry, "unmatched", &response);
+        return;
+    }
+
     // Lectura del body en el caso de ser necesario
     let mut body = vec![0; content_length];
     if content_length > 0 {
-        buf_reader.read_exact(&mut body).unwrap();
+        if let Err(e) = buf_reader.read_exact(&mut body) {
+            error!("Error reading body from stream: {}", e);
+            return;
+        }
     }
 
+    state.metrics.bytes_in(headers.len() + 2 + body.len());
+    let _in_flight = state.metrics.in_flight();
+    let mut entry = AccessEntry::new(start, remote_addr, headers.lines().next().unwrap_or(""));
+
     // Combina headers y body para parsear la solicitud completa
     let request_str = format!("{}\r\n{}", headers, String::from_utf8_lossy(&body));
 
     // Intenta parsear la solicitud
     match parse_request(&request_str) {
-        Ok(request) => {
-            println!("Request Parsed: {:?}", request);
+        Ok(mut request) => {
+            request.remote_addr = remote_addr;
 
-            let key = RouterKey { path: request.path.clone(), method: request.method.clone() };
+            // Id del request, llega a los controllers en el header X-Request-Id y se devuelve en el response
+            let request_id = access_log::request_id(&request);
+            request.headers.retain(|name, _| !name.eq_ignore_ascii_case("X-Request-Id"));
+            request.headers.insert("X-Request-Id".to_string(), request_id.clone());
+            let _request = info_span!("request", method = %request.method, path = %request.path, id = %request_id).entered();
+            let route_label = route_label(state, &request);
+
+            // Las cookies firmadas o cifradas se verifican antes de llegar a los controllers
+            if let Some(ref policy) = state.cookie_policy {
+                policy.unseal_request(&mut request);
+            }
 
-            let controller = (controllers).get(&key);
+            entry.request(&request);
+
+            // Probes de liveness y readiness, no pasan por los middlewares (sesiones, auth, rate limit)
+            if let Some((ref liveness, ref readiness)) = state.health_paths {
+                if request.method == "GET" && (request.path == *liveness || request.path == *readiness) {
+                    let response = if request.path == *liveness {
+                        health::liveness()
+                    } else {
+                        health::readiness(&state.readiness_checks, state.shutting_down.load(Ordering::SeqCst))
+                    };
+                    write_response(&mut writer, &response, &state.metrics);
+                    record(state, &entry, &route_label, &response);
+                    return;
+                }
+            }
+
+            // El request pasa por los middlewares antes de llegar al controller de la ruta
+            // El usuario se toma al final de la cadena, cuando los middlewares ya autenticaron
+            let user = RefCell::new(None);
+            let upgrade = RefCell::new(None);
+            let route = |request: parser::Request| {
+                *user.borrow_mut() = request.identity.as_ref().map(|identity| identity.subject.clone());
+                let key = RouterKey { path: request.path.clone(), method: request.method.clone() };
+
+                // Endpoint de métricas integrado, con los middlewares de la ruta (ej: require_roles)
+                if request.method == "GET" && state.metrics_path.as_ref() == Some(&request.path) {
+                    let render = |_: parser::Request| {
+                        let mut response = create_response(200, Some(state.metrics.render(&state.pool_stats)), None::<HashMap<String, String>>);
+                        response.headers.insert("Content-Type".to_string(), "text/plain; version=0.0.4; charset=utf-8".to_string());
+                        response
+                    };
+                    return match state.route_middlewares.get(&key) {
+                        Some(middlewares) => Next::new(middlewares, &render).run(request),
+                        None => render(request),
+                    };
+                }
+
+                // Upgrade a WebSocket o stream de eventos si la ruta lo permite,
+                // después de los middlewares globales y de la ruta
+                let handler = match state.websockets.get(&request.path) {
+                    Some(handler) if websocket::is_upgrade_request(&request) => Some(UpgradeHandler::WebSocket(*handler)),
+                    _ if request.method == "GET" => state.event_streams.get(&request.path).map(|handler| UpgradeHandler::Events(*handler)),
+                    _ => None,
+                };
+                if let Some(handler) = handler {
+                    let accept = |request: parser::Request| accept_upgrade(state, handler, request, &upgrade);
+                    return match state.route_middlewares.get(&key) {
+                        Some(middlewares) => Next::new(middlewares, &accept).run(request),
+                        None => accept(request),
+                    };
+                }
 
-            let response: Response;
+                match state.router.get(&key) {
+                    // Los middlewares de la ruta corren después de los globales
+                    Some(func) => match state.route_middlewares.get(&key) {
+                        Some(middlewares) => Next::new(middlewares, func).run(request),
+                        None => (func)(request),
+                    },
+                    None => create_response(404, Some("[Error]: Route not found".to_string()), None::<HashMap<String, String>>),
+                }
+            };
+            // Un panic en un controller o middleware se contesta con 500 y el worker sigue atendiendo
+            let mut response: Response = match panic::catch_unwind(AssertUnwindSafe(|| Next::new(&state.middlewares, &route).run(request))) {
+                Ok(response) => response,
+                Err(payload) => {
+                    error!("Controller panicked: {}", panic_message(&*payload));
+                    create_response(500, Some("[Error]: Internal server error".to_string()), None::<HashMap<String, String>>)
+                }
+            };
+            response.headers.insert("X-Request-Id".to_string(), request_id);
 
-            match controller {
-                Some(func) => response = (func)(request),
-                None => response = create_response(404, Some("[Error]: Route not found".to_string()), None::<HashMap<String, String>>),
+            if let Some(ref policy) = state.cookie_policy {
+                policy.seal_response(&mut response);
             }
 
-            // Respuesta de ejemplo (Cambiar)
-            // let response = create_response(200, Some("Hello, World!".to_string()));
-            let mut cookies_str = String::from("");
-            if let Some(cookies) = &response.cookies {
-                for (key, value) in cookies.iter() {
-                    let cookie_str = format!("Set-Cookie: {}={}\r\n", key, value);
-                    cookies_str.push_str(&cookie_str);
-                } 
+            // La conexión solo pasa al handler si los middlewares dejaron pasar el response del upgrade
+            let upgrade = upgrade.into_inner().filter(|upgrade| response.status_code == upgrade.handler.status());
+            entry.user = user.into_inner();
+
+            match upgrade {
+                Some(upgrade) => {
+                    record(state, &entry, &route_label, &response);
+                    upgrade.start(&response, buf_reader, writer, &state.metrics);
+                }
+                None => {
+                    // Envía la respuesta al cliente
+                    write_response(&mut writer, &response, &state.metrics);
+                    record(state, &entry, &route_label, &response);
+                }
             }
-            // Envía la respuesta al cliente
-            let response_str = format!(
-                "HTTP/1.1 {} OK\r\nContent-Length: {}\r\n{}\r\n{}",
-                response.status_code,
-                response.body.as_ref().map(|b| b.len()).unwrap_or(0),
-                cookies_str,
-                response.body.unwrap_or_default()
-            );
-
-            stream.write_all(response_str.as_bytes()).unwrap();
-            stream.flush().unwrap();
         }
         Err(e) => {
             // Si hay un error al parsear, envía un error 400
             let response = create_response(400, Some(format!("[Error]: Error parsing request: {}", e)), None::<HashMap<String, String>>);
-            let response_str = format!(
-                "HTTP/1.1 {} Bad Request\r\nContent-Length: {}\r\n\r\n{}",
-                response.status_code,
-                response.body.as_ref().map(|b| b.len()).unwrap_or(0),
-                response.body.unwrap_or_default()
-            );
+            write_response(&mut writer, &response, &state.metrics);
+            record(state, &entry, "unmatched", &response);
+        }
+    }
+}
+
+// Valida el upgrade y reserva un lugar para la conexión; si ya no hay lugares se contesta 503
+fn accept_upgrade(state: &ServerState, handler: UpgradeHandler, request: Request, upgrade: &RefCell<Option<Upgrade>>) -> Response {
+    let response = match handler {
+        UpgradeHandler::WebSocket(_) => websocket::handshake(&request),
+        UpgradeHandler::Events(_) => sse::response(),
+    };
+    if response.status_code != handler.status() {
+        return response;
+    }
+    match UpgradeSlots::acquire(&state.upgrades) {
+        Some(slot) => {
+            *upgrade.borrow_mut() = Some(Upgrade { handler, request, slot });
+            response
+        }
+        None => {
+            debug!("Too many upgraded connections, rejecting upgrade");
+            let mut response = create_response(503, Some("[Error]: Too many open connections, try again later".to_string()), None::<HashMap<String, String>>);
+            response.headers.insert("Retry-After".to_string(), OVERLOAD_RETRY_AFTER.to_string());
+            response
+        }
+    }
+}
+
+// Contesta 503 a una conexión que no entró en la cola del pool, sin leer el request
+// (corre en el thread de accept, entonces no se espera al cliente)
+fn reject_connection(mut stream: Stream, state: &ServerState) {
+    debug!("Pool queue full, rejecting connection from {}", stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_else(|| "local".to_string()));
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+    let mut response = create_response(503, Some("[Error]: Server overloaded, try again later".to_string()), None::<HashMap<String, String>>);
+    response.headers.insert("Retry-After".to_string(), OVERLOAD_RETRY_AFTER.to_string());
+    response.headers.insert("Connection".to_string(), "close".to_string());
+    write_response(&mut stream, &response, &state.metrics);
+    state.metrics.observe_request("OTHER", "unmatched", 503, Duration::ZERO);
+}
+
+// Ruta registrada que atiende el request, para las métricas (los paths desconocidos se agrupan)
+fn route_label(state: &ServerState, request: &Request) -> String {
+    let key = RouterKey { path: request.path.clone(), method: request.method.clone() };
+    let known = state.router.contains_key(&key)
+        || state.websockets.contains_key(&request.path)
+        || state.event_streams.contains_key(&request.path)
+        || state.metrics_path.as_ref() == Some(&request.path)
+        || state.health_paths.as_ref().map(|(liveness, readiness)| request.path == *liveness || request.path == *readiness).unwrap_or(false);
+    if known { request.path.clone() } else { "unmatched".to_string() }
+}
 
-            stream.write_all(response_str.as_bytes()).unwrap();
-            stream.flush().unwrap();
+// Registra un request respondido en el access log y en las métricas
+fn record(state: &ServerState, entry: &AccessEntry, route: &str, response: &Response) {
+    if let Some(ref log) = state.access_log {
+        log.log(entry, response.status_code, response.body.as_ref().map(|body| body.len()).unwrap_or(0));
+    }
+    state.metrics.observe_request(entry.method(), route, response.status_code, entry.elapsed());
+}
+
+// Escribe el response en el stream, devuelve false si la escritura falló
+fn write_response(stream: &mut impl Write, response: &Response, metrics: &Metrics) -> bool {
+    let response_str = format_response(response);
+    match stream.write_all(response_str.as_bytes()).and_then(|_| stream.flush()) {
+        Ok(_) => {
+            metrics.bytes_out(response_str.len());
+            true
+        }
+        Err(e) => {
+            error!("Error writing response: {}", e);
+            false
         }
     }
 }
 
 pub struct HttpServer {
     pool: ThreadPool,
-    router: HashMap<RouterKey, Controller>
+    router: HashMap<RouterKey, Controller>,
+    websockets: HashMap<String, WebSocketHandler>,
+    event_streams: HashMap<String, EventStreamHandler>,
+    max_upgrades: usize,
+    cookie_policy: Option<Arc<CookiePolicy>>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    route_middlewares: HashMap<RouterKey, Vec<Arc<dyn Middleware>>>,
+    access_log: Option<Arc<AccessLog>>,
+    metrics: Arc<Metrics>,
+    metrics_path: Option<String>,
+    health_paths: Option<(String, String)>,
+    readiness_checks: Vec<(String, Arc<dyn HealthCheck>)>,
+    shutdown_drain: Option<Duration>,
+    max_body_size: Option<usize>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
 }
 
 impl HttpServer {
     // Constructor
     pub fn new(pool_size: usize) -> HttpServer {
-        HttpServer { pool: ThreadPool::new(pool_size), router: HashMap::new() }
+        HttpServer::with_pool(ThreadPool::new(pool_size))
+    }
+
+    // Constructor con un pool ya configurado, ej: ThreadPool::builder().min(4).max(64).build()
+    pub fn with_pool(pool: ThreadPool) -> HttpServer {
+        HttpServer {
+            pool,
+            router: HashMap::new(),
+            websockets: HashMap::new(),
+            event_streams: HashMap::new(),
+            max_upgrades: DEFAULT_MAX_UPGRADES,
+            cookie_policy: None,
+            middlewares: Vec::new(),
+            route_middlewares: HashMap::new(),
+            access_log: None,
+            metrics: Arc::new(Metrics::new()),
+            metrics_path: None,
+            health_paths: None,
+            readiness_checks: Vec::new(),
+            shutdown_drain: None,
+            max_body_size: None,
+            read_timeout: None,
+            write_timeout: None,
+        }
     }
 
     // Add routes with controllers
@@ -142,43 +519,256 @@ impl HttpServer {
         self.router.insert(key, controller);
     }
 
+    // Add WebSocket routes (se aceptan solo requests GET con el handshake de RFC 6455)
+    pub fn websocket(&mut self, path: &str, handler: WebSocketHandler) {
+        self.websockets.insert(path.to_string(), handler);
+    }
+
+    // Add Server-Sent Events routes (solo GET, la conexión se mantiene abierta)
+    pub fn events(&mut self, path: &str, handler: EventStreamHandler) {
+        self.event_streams.insert(path.to_string(), handler);
+    }
+
+    // Máximo de WebSockets y event streams abiertos al mismo tiempo (cada uno ocupa un thread fuera del pool).
+    // Los upgrades que pasen el límite se contestan con 503.
+    pub fn max_upgrades(&mut self, max: usize) {
+        self.max_upgrades = max;
+    }
+
+    // Configura las cookies que se firman o cifran
+    pub fn cookie_policy(&mut self, policy: CookiePolicy) {
+        self.cookie_policy = Some(Arc::new(policy));
+    }
+
+    // Agrega un middleware global, se ejecutan en el orden en que se agregan
+    pub fn middleware(&mut self, middleware: impl Middleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    // Agrega un middleware que solo corre para una ruta (method + path)
+    pub fn route_middleware(&mut self, method: &str, path: &str, middleware: impl Middleware + 'static) {
+        let key = RouterKey { path: path.to_string(), method: method.to_string() };
+        self.route_middlewares.entry(key).or_default().push(Arc::new(middleware));
+    }
+
+    // Configura el access log (una línea por request)
+    pub fn access_log(&mut self, log: AccessLog) {
+        self.access_log = Some(Arc::new(log));
+    }
+
+    // Expone las métricas del servidor en formato de Prometheus en el path indicado (ej: "/metrics").
+    // El endpoint pasa por los middlewares globales y por los de la ruta: sin ellos es público,
+    // protegerlo con require_roles("GET", path, ..) o route_middleware.
+    pub fn metrics(&mut self, path: &str) {
+        self.metrics_path = Some(path.to_string());
+    }
+
+    // Expone los probes de liveness y readiness (ej: "/healthz" y "/readyz")
+    pub fn health(&mut self, liveness_path: &str, readiness_path: &str) {
+        self.health_paths = Some((liveness_path.to_string(), readiness_path.to_string()));
+    }
+
+    // Agrega un check que se evalúa en cada request al endpoint de readiness
+    pub fn readiness_check(&mut self, name: &str, check: impl HealthCheck + 'static) {
+        self.readiness_checks.push((name.to_string(), Arc::new(check)));
+    }
+
+    // Check de readiness que falla si hay más de max_queue conexiones esperando un worker
+    pub fn pool_check(&self, max_queue: usize) -> PoolCheck {
+        PoolCheck::new(self.pool.stats(), max_queue)
+    }
+
+    // Al recibir SIGTERM o SIGINT el servidor deja de estar ready, espera `drain` para que el
+    // orquestador deje de enviarle tráfico y después deja de aceptar conexiones.
+    // Los requests en curso terminan antes de que se apague el pool.
+    pub fn graceful_shutdown(&mut self, drain: Duration) {
+        self.shutdown_drain = Some(drain);
+    }
+
+    // Limita las conexiones que esperan un worker. Con la cola llena se aplica `overload`:
+    // Block detiene el accept, Reject y DropOldest contestan 503 con Retry-After a la conexión descartada.
+    pub fn queue_limit(&mut self, capacity: usize, overload: Overload) {
+        self.pool.set_queue_limit(capacity, overload);
+    }
+
+    // Tamaño máximo del body, los requests más grandes se rechazan con 413
+    pub fn max_body_size(&mut self, bytes: usize) {
+        self.max_body_size = Some(bytes);
+    }
+
+    // Timeouts de lectura y escritura de cada conexión
+    pub fn timeouts(&mut self, read: Duration, write: Duration) {
+        self.read_timeout = Some(read);
+        self.write_timeout = Some(write);
+    }
+
+    // Exige que el cliente tenga alguno de los roles para acceder a la ruta
+    pub fn require_roles(&mut self, method: &str, path: &str, roles: &[&str]) {
+        self.route_middleware(method, path, RequireRoles::new(roles));
+    }
+
     // Start listening to ports
-    pub fn listen(&self, port: u16, mut cb: impl FnMut() + 'static) {
-        // Un Listener TCP para el puerto indicado
-        let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).unwrap();
-        // Se hace un assert para ver si el listener esta en el puerto que se indicó
-        assert_eq!(
-            listener.local_addr().unwrap(),
-            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port)),
-            "[Error]: Could not open the server at the specified port"
-        );
+    // Acepta cualquier ToSocketAddrs: "127.0.0.1:8080", ("::", 8080), &[addr1, addr2][..], etc.
+    // Se abre un listener por cada dirección resuelta y el callback recibe las direcciones realmente
+    // abiertas (útil con el puerto 0, donde el sistema elige el puerto).
+    pub fn listen<A: ToSocketAddrs>(&self, addrs: A, mut cb: impl FnMut(&[SocketAddr])) -> io::Result<()> {
+        let (listeners, local_addrs) = bind_tcp(addrs)?;
         // Correr el callback de que se logro abrir el puerto
-        (cb)();
+        (cb)(&local_addrs);
+        self.serve(Arc::new(Lifecycle::new(listeners)));
+        Ok(())
+    }
+
+    // Escucha en un Unix domain socket, el callback recibe el path del socket ya abierto.
+    // El archivo del socket se borra al apagar el servidor.
+    #[cfg(unix)]
+    pub fn listen_unix(&self, socket: UnixSocket, mut cb: impl FnMut(&Path)) -> io::Result<()> {
+        let listener = socket.bind()?;
+        (cb)(socket.path());
+        self.serve(Arc::new(Lifecycle::new(vec![Listener::Unix(listener, socket.path().to_path_buf())])));
+        Ok(())
+    }
+
+    // Igual que listen pero el servidor corre en su propio thread y se controla con el ServerHandle.
+    // Con el puerto 0 se pueden correr varios servidores en el mismo proceso (ej: en tests).
+    pub fn start<A: ToSocketAddrs>(self, addrs: A) -> io::Result<ServerHandle> {
+        let (listeners, local_addrs) = bind_tcp(addrs)?;
+        self.spawn(Lifecycle::new(listeners), local_addrs)
+    }
+
+    // Igual que listen_unix pero sin bloquear, ver start
+    #[cfg(unix)]
+    pub fn start_unix(self, socket: UnixSocket) -> io::Result<ServerHandle> {
+        let listener = socket.bind()?;
+        self.spawn(Lifecycle::new(vec![Listener::Unix(listener, socket.path().to_path_buf())]), Vec::new())
+    }
+
+    fn spawn(self, lifecycle: Lifecycle, local_addrs: Vec<SocketAddr>) -> io::Result<ServerHandle> {
+        let lifecycle = Arc::new(lifecycle);
+        let serving = Arc::clone(&lifecycle);
+        let thread = thread::Builder::new().name("http-server".to_string()).spawn(move || self.serve(serving))?;
+        Ok(ServerHandle::new(local_addrs, lifecycle, thread))
+    }
+
+    // Atiende las conexiones de los listeners hasta que se pida el apagado
+    fn serve(&self, lifecycle: Arc<Lifecycle>) {
+        // Las rutas se comparten entre los threads con un Arc en vez de copiarlas en cada conexión
+        let state = Arc::new(ServerState {
+            router: self.router.clone(),
+            websockets: self.websockets.clone(),
+            event_streams: self.event_streams.clone(),
+            upgrades: Arc::new(UpgradeSlots::new(self.max_upgrades)),
+            cookie_policy: self.cookie_policy.clone(),
+            middlewares: self.middlewares.clone(),
+            route_middlewares: self.route_middlewares.clone(),
+            access_log: self.access_log.clone(),
+            metrics: Arc::clone(&self.metrics),
+            metrics_path: self.metrics_path.clone(),
+            pool_stats: self.pool.stats(),
+            health_paths: self.health_paths.clone(),
+            readiness_checks: self.readiness_checks.clone(),
+            shutting_down: Arc::clone(&lifecycle.shutting_down),
+            max_body_size: self.max_body_size,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+        });
+
+        if let Some(drain) = self.shutdown_drain {
+            watch_signals(drain, Arc::clone(&lifecycle));
+        }
+
+        // Un thread de accept por listener, todos comparten el pool
+        thread::scope(|scope| {
+            for listener in lifecycle.listeners.iter() {
+                let (state, stopping) = (&state, &lifecycle.stopping);
+                scope.spawn(move || self.accept_loop(listener, state, stopping));
+            }
+            lifecycle.set_running();
+        });
+
+        info!("Shutting down.");
+    }
 
-        // Main listener loop
-        for stream in listener.incoming() {
-            match stream {
+    // Main listener loop
+    // El accept no bloquea, así el loop termina con `stopping` aunque nadie se conecte
+    fn accept_loop(&self, listener: &Listener, state: &Arc<ServerState>, stopping: &AtomicBool) {
+        if let Err(e) = listener.set_nonblocking() {
+            error!("Could not set up the listener on {}: {}", listener.describe(), e);
+            return;
+        }
+        while !stopping.load(Ordering::SeqCst) {
+            match listener.accept() {
                 // Caso de recibir un stream al puerto
                 Ok(stream) => {
-                    // Duplicacion de controllers para cada thread 
-                    // No se puede pasar directamente los controllers por políticas estrictas de contexto de Rust
-                    let mut controllers: HashMap<RouterKey, Controller> = HashMap::new();
-                    controllers.clear();
-                    controllers.extend(self.router.clone().into_iter());
-
-                    println!("[Log]: Connection Established");
-                    // Ejecutar el handler de las conexiones en uno de los threads del pool
-                    self.pool.execute( move || {
-                        handle_connection(stream, &controllers);
-                    });
+                    state.metrics.connection_accepted();
+                    let state = Arc::clone(state);
+
+                    // Ejecutar el handler de las conexiones en uno de los threads del pool.
+                    // El stream lo toma el worker o, si la cola está llena, el rechazo con 503.
+                    let stream = Arc::new(Mutex::new(Some(stream)));
+                    let rejected = (Arc::clone(&stream), Arc::clone(&state));
+                    self.pool.execute_or(
+                        move || {
+                            if let Some(stream) = stream.lock().unwrap().take() {
+                                handle_connection(stream, &state);
+                            }
+                        },
+                        move || {
+                            if let Some(stream) = rejected.0.lock().unwrap().take() {
+                                reject_connection(stream, &rejected.1);
+                            }
+                        },
+                    );
                 }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
                 Err(e) => {
-                    println!("[Error]: Failed to establish a connection: {}", e);
+                    state.metrics.connection_failed();
+                    error!("Failed to establish a connection on {}: {}", listener.describe(), e);
+                    // Ej: sin descriptores libres, reintentar enseguida solo llenaría el log
+                    thread::sleep(ACCEPT_POLL);
                 }
             }
         }
+    }
 
-        println!("Shutting down.");
+}
+
+// Abre un listener por cada dirección resuelta
+fn bind_tcp<A: ToSocketAddrs>(addrs: A) -> io::Result<(Vec<Listener>, Vec<SocketAddr>)> {
+    let mut listeners = Vec::new();
+    let mut local_addrs = Vec::new();
+    for addr in addrs.to_socket_addrs()? {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        listeners.push(Listener::Tcp(listener, local_addr));
+        local_addrs.push(local_addr);
+    }
+    if listeners.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no addresses to listen on"));
     }
+    Ok((listeners, local_addrs))
+}
 
-}
\ No newline at end of file
+// Espera SIGTERM/SIGINT en un thread: marca el servidor como no ready, espera el drain y detiene el accept
+fn watch_signals(drain: Duration, lifecycle: Arc<Lifecycle>) {
+    let mut signals = match Signals::new([SIGTERM, SIGINT]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!("Could not register signal handlers: {}", e);
+            return;
+        }
+    };
+    // Si el servidor se apaga por otro lado (ServerHandle::shutdown) el thread termina
+    lifecycle.watch(signals.handle());
+    let spawned = thread::Builder::new().name("signals".to_string()).spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            info!("Received signal {}, draining for {:?} before shutting down", signal, drain);
+            lifecycle.shutting_down.store(true, Ordering::SeqCst);
+            thread::sleep(drain);
+            lifecycle.stop();
+        }
+    });
+    if let Err(e) = spawned {
+        error!("Could not spawn signal thread: {}", e);
+    }
+}
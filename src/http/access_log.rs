@@ -0,0 +1,276 @@
+// Imports
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+extern crate rand;
+extern crate serde_json;
+extern crate tracing;
+use self::rand::Rng;
+use self::tracing::error;
+
+use crate::http::date::{clf_date, rfc3339};
+use crate::http::parser::Request;
+
+// Headers que nunca se escriben en el log
+const SENSITIVE_HEADERS: [&str; 5] = ["Authorization", "Proxy-Authorization", "Cookie", "Set-Cookie", "X-API-Key"];
+
+/// Formato de las líneas del access log
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    // Common Log Format: host ident user [fecha] "request" status bytes
+    Common,
+    // Common más "referer" "user-agent"
+    Combined,
+    // Un objeto JSON por línea con todos los campos (latencia, request id, etc.)
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(format: &str) -> Option<LogFormat> {
+        match format.to_ascii_lowercase().as_str() {
+            "common" | "clf" => Some(LogFormat::Common),
+            "combined" => Some(LogFormat::Combined),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Destino de las líneas del access log
+pub trait LogSink: Send + Sync {
+    fn write_line(&self, line: &str);
+}
+
+/// Escribe el log en stdout
+pub struct StdoutSink;
+
+impl LogSink for StdoutSink {
+    fn write_line(&self, line: &str) {
+        println!("{}", line);
+    }
+}
+
+/// Escribe el log en un archivo que se rota al llegar a max_bytes.
+/// Los archivos anteriores quedan como "archivo.1" (el más reciente) hasta "archivo.<max_files>".
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn new(path: &str, max_bytes: u64, max_files: usize) -> io::Result<RotatingFile> {
+        let path = PathBuf::from(path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { path, max_bytes, max_files, file: Mutex::new((file, size)) })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    // Mueve archivo.N-1 -> archivo.N, ..., archivo -> archivo.1 y abre un archivo nuevo
+    fn rotate(&self) -> io::Result<File> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        OpenOptions::new().create(true).append(true).open(&self.path)
+    }
+}
+
+impl LogSink for RotatingFile {
+    fn write_line(&self, line: &str) {
+        let mut file = self.file.lock().unwrap();
+        if file.1 > 0 && file.1 + line.len() as u64 + 1 > self.max_bytes {
+            match self.rotate() {
+                Ok(new_file) => *file = (new_file, 0),
+                Err(e) => error!("Could not rotate access log '{}': {}", self.path.display(), e),
+            }
+        }
+        match writeln!(file.0, "{}", line) {
+            Ok(_) => file.1 += line.len() as u64 + 1,
+            Err(e) => error!("Could not write access log '{}': {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Datos de un request para el access log, se juntan antes de que el request pase a los controllers
+pub struct AccessEntry {
+    start: Instant,
+    remote_addr: Option<SocketAddr>,
+    request_line: String,
+    method: String,
+    target: String,
+    pub user: Option<String>,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    request_id: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl AccessEntry {
+    // Entrada con lo que se sabe antes de parsear el request (la primera línea y el cliente)
+    pub fn new(start: Instant, remote_addr: Option<SocketAddr>, request_line: &str) -> AccessEntry {
+        let mut parts = request_line.split_whitespace();
+        AccessEntry {
+            start,
+            remote_addr,
+            request_line: request_line.to_string(),
+            method: parts.next().unwrap_or("-").to_string(),
+            target: parts.next().unwrap_or("-").to_string(),
+            user: None,
+            user_agent: None,
+            referer: None,
+            request_id: None,
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    // Tiempo desde que se empezó a atender el request
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // Completa la entrada con los datos del request parseado
+    pub fn request(&mut self, request: &Request) {
+        self.user_agent = request.header("User-Agent").cloned();
+        self.referer = request.header("Referer").cloned();
+        self.request_id = request.header("X-Request-Id").cloned();
+        self.user = request.identity.as_ref().map(|identity| identity.subject.clone());
+        self.headers = request.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        self.headers.sort();
+    }
+}
+
+/// Id para correlacionar un request con sus logs. Se respeta el X-Request-Id del cliente si es válido.
+pub fn request_id(request: &Request) -> String {
+    match request.header("X-Request-Id") {
+        Some(id) if !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.') => id.clone(),
+        _ => {
+            let bytes: [u8; 16] = rand::thread_rng().gen();
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+    }
+}
+
+/// Access log: una línea por request en el formato y destino configurados
+pub struct AccessLog {
+    format: LogFormat,
+    sink: Box<dyn LogSink>,
+    log_headers: bool,
+    redacted: Vec<String>,
+}
+
+impl AccessLog {
+    pub fn new(format: LogFormat, sink: impl LogSink + 'static) -> AccessLog {
+        AccessLog {
+            format,
+            sink: Box::new(sink),
+            log_headers: false,
+            redacted: SENSITIVE_HEADERS.iter().map(|header| header.to_string()).collect(),
+        }
+    }
+
+    // Incluye los headers del request en el formato JSON (los sensibles se reemplazan por "[REDACTED]")
+    pub fn log_headers(mut self, log_headers: bool) -> AccessLog {
+        self.log_headers = log_headers;
+        self
+    }
+
+    // Agrega un header a la lista de headers que no se escriben
+    pub fn redact_header(mut self, header: &str) -> AccessLog {
+        self.redacted.push(header.to_string());
+        self
+    }
+
+    // Escribe la línea de un request ya respondido
+    pub fn log(&self, entry: &AccessEntry, status: u16, bytes: usize) {
+        let latency = entry.start.elapsed();
+        let line = match self.format {
+            LogFormat::Common => self.common(entry, status, bytes),
+            LogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                self.common(entry, status, bytes),
+                escape(entry.referer.as_deref().unwrap_or("-")),
+                escape(entry.user_agent.as_deref().unwrap_or("-"))
+            ),
+            LogFormat::Json => self.json(entry, status, bytes, latency),
+        };
+        self.sink.write_line(&line);
+    }
+
+    fn common(&self, entry: &AccessEntry, status: u16, bytes: usize) -> String {
+        format!(
+            "{} - {} [{}] \"{}\" {} {}",
+            entry.remote_addr.map(|addr| addr.ip().to_string()).unwrap_or_else(|| "-".to_string()),
+            entry.user.as_deref().map(escape).unwrap_or_else(|| "-".to_string()),
+            clf_date(SystemTime::now()),
+            escape(&entry.request_line),
+            status,
+            if bytes == 0 { "-".to_string() } else { bytes.to_string() }
+        )
+    }
+
+    fn json(&self, entry: &AccessEntry, status: u16, bytes: usize, latency: Duration) -> String {
+        let mut line = serde_json::json!({
+            "time": rfc3339(SystemTime::now()),
+            "request_id": entry.request_id,
+            "peer": entry.remote_addr.map(|addr| addr.to_string()),
+            "user": entry.user,
+            "method": entry.method,
+            "path": entry.target,
+            "status": status,
+            "bytes": bytes,
+            "latency_ms": latency.as_secs_f64() * 1000.0,
+            "user_agent": entry.user_agent,
+            "referer": entry.referer,
+        });
+        if self.log_headers {
+            let headers: serde_json::Map<String, serde_json::Value> = entry
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = if self.redacted.iter().any(|header| header.eq_ignore_ascii_case(name)) { "[REDACTED]" } else { value };
+                    (name.clone(), serde_json::Value::String(value.to_string()))
+                })
+                .collect();
+            line["headers"] = serde_json::Value::Object(headers);
+        }
+        line.to_string()
+    }
+}
+
+// Escapa comillas, barras y caracteres de control para que un campo no rompa la línea
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,454 @@
+// Imports
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+extern crate base64;
+extern crate hmac;
+extern crate md5;
+extern crate rand;
+extern crate sha2;
+use self::base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use self::hmac::{Hmac, Mac};
+use self::md5::Md5;
+use self::rand::Rng;
+use self::sha2::{Digest, Sha256};
+
+use crate::http::jwt::{unix_now, Jwt, JwtError};
+use crate::http::middleware::{Middleware, Next};
+use crate::http::parser::{create_response, Request, Response};
+
+/// Identidad autenticada del cliente, disponible en Request.identity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub subject: String,
+    pub roles: Vec<String>,
+}
+
+impl Identity {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+/// Middleware que exige alguno de los roles indicados.
+/// Responde 401 si el cliente no está autenticado y 403 si no tiene ninguno de los roles.
+pub struct RequireRoles {
+    roles: Vec<String>,
+}
+
+impl RequireRoles {
+    pub fn new(roles: &[&str]) -> RequireRoles {
+        RequireRoles { roles: roles.iter().map(|role| role.to_string()).collect() }
+    }
+}
+
+impl Middleware for RequireRoles {
+    fn handle(&self, request: Request, next: Next) -> Response {
+        let allowed = match request.identity {
+            Some(ref identity) => self.roles.iter().any(|role| identity.has_role(role)),
+            None => return unauthorized("Bearer", "Authentication required"),
+        };
+        if !allowed {
+            return create_response(403, Some("[Error]: Insufficient role for this route".to_string()), None::<HashMap<String, String>>);
+        }
+        next.run(request)
+    }
+}
+
+// Response 401 con el challenge correspondiente en WWW-Authenticate
+pub fn unauthorized(challenge: &str, message: &str) -> Response {
+    let mut response = create_response(401, Some(format!("[Error]: {}", message)), None::<HashMap<String, String>>);
+    response.headers.insert("WWW-Authenticate".to_string(), challenge.to_string());
+    response
+}
+
+/// Middleware que valida "Authorization: Bearer <jwt>".
+/// Los requests sin el header siguen como anónimos; un token inválido se rechaza con 401.
+pub struct BearerAuth {
+    jwt: Arc<Jwt>,
+}
+
+impl BearerAuth {
+    pub fn new(jwt: Arc<Jwt>) -> BearerAuth {
+        BearerAuth { jwt }
+    }
+}
+
+impl Middleware for BearerAuth {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        let token = match request.header("Authorization") {
+            Some(value) if value.get(..7).map(|scheme| scheme.eq_ignore_ascii_case("bearer ")).unwrap_or(false) => value[7..].trim().to_string(),
+            _ => return next.run(request),
+        };
+
+        match self.jwt.decode(&token) {
+            Ok(claims) => {
+                request.identity = Some(Identity { subject: claims.sub, roles: claims.roles });
+                next.run(request)
+            }
+            Err(error) => {
+                let description = match error {
+                    JwtError::Expired => "Token expired",
+                    JwtError::InvalidSignature => "Invalid token signature",
+                    JwtError::UnsupportedAlgorithm => "Unsupported token algorithm",
+                    JwtError::Malformed => "Malformed token",
+                };
+                unauthorized(&format!("Bearer error=\"invalid_token\", error_description=\"{}\"", description), description)
+            }
+        }
+    }
+}
+
+/// Middleware que valida API keys enviadas en un header (por defecto "X-API-Key").
+/// `lookup` devuelve la identidad asociada a la llave; una llave desconocida se rechaza con 401.
+pub struct ApiKeyAuth {
+    header: String,
+    lookup: fn(&str) -> Option<Identity>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(lookup: fn(&str) -> Option<Identity>) -> ApiKeyAuth {
+        ApiKeyAuth { header: "X-API-Key".to_string(), lookup }
+    }
+
+    // Cambia el header del que se lee la llave
+    pub fn header(mut self, header: &str) -> ApiKeyAuth {
+        self.header = header.to_string();
+        self
+    }
+}
+
+impl Middleware for ApiKeyAuth {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        let key = match request.header(&self.header) {
+            Some(key) => key.trim().to_string(),
+            None => return next.run(request),
+        };
+
+        match (self.lookup)(&key) {
+            Some(identity) => {
+                request.identity = Some(identity);
+                next.run(request)
+            }
+            None => unauthorized("ApiKey", "Invalid API key"),
+        }
+    }
+}
+
+/// Algoritmos de hash de Digest (RFC 7616)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Sha256 => "SHA-256",
+        }
+    }
+
+    // Hash en hexadecimal en minúsculas
+    pub fn hash(&self, data: &str) -> String {
+        let bytes = match self {
+            DigestAlgorithm::Md5 => Md5::digest(data.as_bytes()).to_vec(),
+            DigestAlgorithm::Sha256 => Sha256::digest(data.as_bytes()).to_vec(),
+        };
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Verificación de credenciales para BasicAuth y DigestAuth
+pub trait CredentialVerifier: Send + Sync {
+    // Verifica usuario y contraseña (Basic)
+    fn verify_password(&self, username: &str, password: &str) -> bool;
+
+    // H(username:realm:password) con el algoritmo indicado (Digest).
+    // None si el usuario no existe o si el verificador no soporta Digest.
+    fn digest_ha1(&self, _username: &str, _realm: &str, _algorithm: DigestAlgorithm) -> Option<String> {
+        None
+    }
+
+    // Roles de la identidad de un usuario autenticado
+    fn roles(&self, _username: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Credenciales fijas en memoria, para proteger rutas internas
+pub struct StaticCredentials {
+    users: HashMap<String, (String, Vec<String>)>,
+}
+
+impl StaticCredentials {
+    pub fn new() -> StaticCredentials {
+        StaticCredentials { users: HashMap::new() }
+    }
+
+    pub fn user(mut self, username: &str, password: &str, roles: &[&str]) -> StaticCredentials {
+        let roles = roles.iter().map(|role| role.to_string()).collect();
+        self.users.insert(username.to_string(), (password.to_string(), roles));
+        self
+    }
+}
+
+impl Default for StaticCredentials {
+    fn default() -> StaticCredentials {
+        StaticCredentials::new()
+    }
+}
+
+impl CredentialVerifier for StaticCredentials {
+    fn verify_password(&self, username: &str, password: &str) -> bool {
+        match self.users.get(username) {
+            Some((expected, _)) => constant_time_eq(expected.as_bytes(), password.as_bytes()),
+            None => false,
+        }
+    }
+
+    fn digest_ha1(&self, username: &str, realm: &str, algorithm: DigestAlgorithm) -> Option<String> {
+        let (password, _) = self.users.get(username)?;
+        Some(algorithm.hash(&format!("{}:{}:{}", username, realm, password)))
+    }
+
+    fn roles(&self, username: &str) -> Vec<String> {
+        self.users.get(username).map(|(_, roles)| roles.clone()).unwrap_or_default()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Valor entre comillas para un challenge (escapa '"' y '\')
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Middleware de autenticación HTTP Basic (RFC 7617).
+/// Exige credenciales válidas; si faltan o son incorrectas responde 401 con el challenge del realm.
+pub struct BasicAuth {
+    realm: String,
+    verifier: Arc<dyn CredentialVerifier>,
+}
+
+impl BasicAuth {
+    pub fn new(realm: &str, verifier: Arc<dyn CredentialVerifier>) -> BasicAuth {
+        BasicAuth { realm: realm.to_string(), verifier }
+    }
+
+    fn challenge(&self) -> Response {
+        unauthorized(&format!("Basic realm={}, charset=\"UTF-8\"", quote(&self.realm)), "Authentication required")
+    }
+}
+
+impl Middleware for BasicAuth {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        let credentials = match request.header("Authorization") {
+            Some(value) if value.get(..6).map(|scheme| scheme.eq_ignore_ascii_case("basic ")).unwrap_or(false) => value[6..].trim().to_string(),
+            _ => return self.challenge(),
+        };
+
+        // "usuario:contraseña" en base64; el usuario no puede contener ':'
+        let decoded = match STANDARD.decode(&credentials).ok().and_then(|bytes| String::from_utf8(bytes).ok()) {
+            Some(decoded) => decoded,
+            None => return self.challenge(),
+        };
+        let (username, password) = match decoded.find(':') {
+            Some(idx) => (&decoded[..idx], &decoded[idx + 1..]),
+            None => return self.challenge(),
+        };
+
+        if !self.verifier.verify_password(username, password) {
+            return self.challenge();
+        }
+        request.identity = Some(Identity { subject: username.to_string(), roles: self.verifier.roles(username) });
+        next.run(request)
+    }
+}
+
+// Tiempo de validez de un nonce de Digest (por defecto)
+const DEFAULT_NONCE_TTL: Duration = Duration::from_secs(300);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Middleware de autenticación HTTP Digest (RFC 7616) con qop="auth".
+/// Los nonces llevan su fecha firmada (no se guardan); los vencidos se rechazan con stale=true
+/// y se registra el último nc usado de cada nonce para evitar que se reutilice una respuesta.
+pub struct DigestAuth {
+    realm: String,
+    algorithm: DigestAlgorithm,
+    verifier: Arc<dyn CredentialVerifier>,
+    secret: [u8; 32],
+    nonce_ttl: u64,
+    // nonce -> (fecha del nonce, último nc)
+    nonce_counts: Mutex<HashMap<String, (u64, u32)>>,
+}
+
+impl DigestAuth {
+    // Por defecto usa SHA-256
+    pub fn new(realm: &str, verifier: Arc<dyn CredentialVerifier>) -> DigestAuth {
+        DigestAuth {
+            realm: realm.to_string(),
+            algorithm: DigestAlgorithm::Sha256,
+            verifier,
+            secret: rand::thread_rng().gen(),
+            nonce_ttl: DEFAULT_NONCE_TTL.as_secs(),
+            nonce_counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Cambia el algoritmo (MD5 solo para clientes que no soportan SHA-256)
+    pub fn algorithm(mut self, algorithm: DigestAlgorithm) -> DigestAuth {
+        self.algorithm = algorithm;
+        self
+    }
+
+    // Tiempo de validez de los nonces, con precisión de segundos (por defecto 5 minutos)
+    pub fn nonce_ttl(mut self, ttl: Duration) -> DigestAuth {
+        self.nonce_ttl = ttl.as_secs();
+        self
+    }
+
+    fn nonce_mac(&self, timestamp: &[u8]) -> Vec<u8> {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.secret).expect("HMAC accepts keys of any size");
+        mac.update(timestamp);
+        mac.update(self.realm.as_bytes());
+        mac.finalize().into_bytes()[..16].to_vec()
+    }
+
+    // Nonce: fecha de emisión + HMAC de la fecha
+    fn new_nonce(&self) -> String {
+        let timestamp = unix_now().to_be_bytes();
+        let mut nonce = timestamp.to_vec();
+        nonce.extend(self.nonce_mac(&timestamp));
+        URL_SAFE_NO_PAD.encode(nonce)
+    }
+
+    // Fecha de emisión de un nonce emitido por este middleware
+    fn nonce_timestamp(&self, nonce: &str) -> Option<u64> {
+        let bytes = URL_SAFE_NO_PAD.decode(nonce).ok()?;
+        if bytes.len() != 24 || !constant_time_eq(&bytes[8..], &self.nonce_mac(&bytes[..8])) {
+            return None;
+        }
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&bytes[..8]);
+        Some(u64::from_be_bytes(timestamp))
+    }
+
+    fn challenge(&self, stale: bool) -> Response {
+        let mut challenge = format!(
+            "Digest realm={}, qop=\"auth\", algorithm={}, nonce={}",
+            quote(&self.realm),
+            self.algorithm.as_str(),
+            quote(&self.new_nonce())
+        );
+        if stale {
+            challenge.push_str(", stale=true");
+        }
+        unauthorized(&challenge, "Authentication required")
+    }
+
+    // Verifica nc > último nc usado con el nonce (y limpia los nonces vencidos)
+    fn check_nonce_count(&self, nonce: &str, issued: u64, nc: u32) -> bool {
+        let now = unix_now();
+        let mut counts = self.nonce_counts.lock().unwrap();
+        counts.retain(|_, (timestamp, _)| timestamp.saturating_add(self.nonce_ttl) >= now);
+        let last = counts.entry(nonce.to_string()).or_insert((issued, 0));
+        if nc <= last.1 {
+            return false;
+        }
+        last.1 = nc;
+        true
+    }
+}
+
+// Parsea los parámetros de "Authorization: Digest k=v, k="v", ..."
+fn parse_auth_params(input: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while let Some(&c) = chars.peek() {
+            if c == ',' || c.is_whitespace() { chars.next(); } else { break; }
+        }
+        let name: String = std::iter::from_fn(|| chars.next_if(|&c| c != '=' && c != ',')).collect();
+        if name.is_empty() {
+            break;
+        }
+        if chars.next() != Some('=') {
+            continue;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            value = std::iter::from_fn(|| chars.next_if(|&c| c != ',')).collect::<String>().trim().to_string();
+        }
+        params.insert(name.trim().to_ascii_lowercase(), value);
+    }
+    params
+}
+
+impl Middleware for DigestAuth {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        let params = match request.header("Authorization") {
+            Some(value) if value.get(..7).map(|scheme| scheme.eq_ignore_ascii_case("digest ")).unwrap_or(false) => parse_auth_params(&value[7..]),
+            _ => return self.challenge(false),
+        };
+        let get = |name: &str| params.get(name).map(|value| value.as_str()).unwrap_or("");
+
+        let algorithm = if get("algorithm").is_empty() { "MD5" } else { get("algorithm") };
+        if get("realm") != self.realm || !algorithm.eq_ignore_ascii_case(self.algorithm.as_str()) || get("qop") != "auth" {
+            return self.challenge(false);
+        }
+
+        // La uri del header debe ser la del request
+        let uri = get("uri");
+        let uri_path = uri.split('?').next().unwrap_or("");
+        if uri_path != request.path.trim_end_matches('?') {
+            return create_response(400, Some("[Error]: Digest uri does not match the request".to_string()), None::<HashMap<String, String>>);
+        }
+
+        let nonce = get("nonce");
+        let issued = match self.nonce_timestamp(nonce) {
+            Some(issued) => issued,
+            None => return self.challenge(false),
+        };
+        if issued.saturating_add(self.nonce_ttl) < unix_now() {
+            return self.challenge(true);
+        }
+
+        let username = get("username");
+        let ha1 = match self.verifier.digest_ha1(username, &self.realm, self.algorithm) {
+            Some(ha1) => ha1,
+            None => return self.challenge(false),
+        };
+        let ha2 = self.algorithm.hash(&format!("{}:{}", request.method, uri));
+        let expected = self.algorithm.hash(&format!("{}:{}:{}:{}:auth:{}", ha1, nonce, get("nc"), get("cnonce"), ha2));
+        if !constant_time_eq(expected.as_bytes(), get("response").to_ascii_lowercase().as_bytes()) {
+            return self.challenge(false);
+        }
+
+        // Una respuesta válida no se puede reutilizar: el nc debe aumentar en cada request
+        let nc = match u32::from_str_radix(get("nc"), 16) {
+            Ok(nc) => nc,
+            Err(_) => return self.challenge(false),
+        };
+        if !self.check_nonce_count(nonce, issued, nc) {
+            return self.challenge(true);
+        }
+
+        let username = username.to_string();
+        request.identity = Some(Identity { roles: self.verifier.roles(&username), subject: username });
+        next.run(request)
+    }
+}
@@ -0,0 +1,193 @@
+// Imports
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+};
+#[cfg(unix)]
+extern crate rand;
+
+/// Stream de una conexión de un cliente (TCP o Unix socket)
+pub trait Connection: Read + Write + Send {
+    // Dirección del cliente, los Unix sockets no tienen
+    fn peer_addr(&self) -> Option<SocketAddr>;
+    fn try_clone(&self) -> io::Result<Stream>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+/// Conexión sin importar el tipo de socket (la usan los WebSockets y los event streams)
+pub type Stream = Box<dyn Connection>;
+
+impl Connection for TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+
+    fn try_clone(&self) -> io::Result<Stream> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    fn try_clone(&self) -> io::Result<Stream> {
+        Ok(Box::new(UnixStream::try_clone(self)?))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// Unix domain socket en el que escucha el servidor, ej: UnixSocket::new("/run/httprust.sock").mode(0o660)
+#[cfg(unix)]
+pub struct UnixSocket {
+    path: PathBuf,
+    mode: Option<u32>,
+}
+
+#[cfg(unix)]
+impl UnixSocket {
+    pub fn new(path: impl AsRef<Path>) -> UnixSocket {
+        UnixSocket { path: path.as_ref().to_path_buf(), mode: None }
+    }
+
+    // Permisos del archivo del socket (por defecto quedan los del umask)
+    pub fn mode(mut self, mode: u32) -> UnixSocket {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Abre el socket. Si quedó un socket de una ejecución anterior que ya nadie atiende se borra;
+    // si otro proceso lo está usando o el path no es un socket se devuelve un error.
+    pub(crate) fn bind(&self) -> io::Result<UnixListener> {
+        if let Ok(metadata) = fs::symlink_metadata(&self.path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and is not a socket", self.path.display())));
+            }
+            match UnixStream::connect(&self.path) {
+                Ok(_) => return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use by another process", self.path.display()))),
+                Err(_) => fs::remove_file(&self.path)?,
+            }
+        }
+        match self.mode {
+            Some(mode) => self.bind_private(mode),
+            None => UnixListener::bind(&self.path),
+        }
+    }
+
+    // Con un modo, el socket se crea en un directorio temporal al que solo accede este usuario,
+    // se le cambian los permisos y recién después se mueve al path final. Así nunca queda expuesto
+    // con los permisos del umask.
+    fn bind_private(&self, mode: u32) -> io::Result<UnixListener> {
+        let parent = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        // Nombre aleatorio: otro listener del proceso, un directorio que quedó de otra ejecución o uno
+        // creado por otro usuario en un directorio compartido no lo pueden ocupar de antemano
+        let mut attempts = 0;
+        let dir = loop {
+            let dir = parent.join(format!(".httprust-{:016x}", rand::random::<u64>()));
+            match fs::DirBuilder::new().mode(0o700).create(&dir) {
+                Ok(()) => break dir,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 8 => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        };
+        let temp = dir.join("s");
+        let bound = UnixListener::bind(&temp).and_then(|listener| {
+            fs::set_permissions(&temp, fs::Permissions::from_mode(mode))?;
+            fs::rename(&temp, &self.path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_file(&temp);
+        let _ = fs::remove_dir(&dir);
+        bound
+    }
+}
+
+// Listener abierto por el servidor
+pub(crate) enum Listener {
+    Tcp(TcpListener, SocketAddr),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    // Siguiente conexión pendiente. El listener no bloquea (WouldBlock si no hay ninguna),
+    // las conexiones sí: en algunos sistemas heredan el modo del listener.
+    pub(crate) fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener, _) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    pub(crate) fn set_nonblocking(&self) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener, _) => listener.set_nonblocking(true),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.set_nonblocking(true),
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Listener::Tcp(_, addr) => addr.to_string(),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => format!("unix:{}", path.display()),
+        }
+    }
+}
+
+// El archivo del socket se borra al cerrar el servidor
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Listener::Unix(_, path) = self {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Imports
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::http::middleware::{Middleware, Next};
+use crate::http::parser::{create_response, Request, Response};
+
+/// Middleware de CORS. Agrega los headers Access-Control-* a los responses de orígenes permitidos
+/// y contesta los preflight (OPTIONS con Access-Control-Request-Method) sin llegar al router.
+///
+/// Los orígenes pueden ser exactos ("https://app.example.com"), con comodines
+/// ("https://*.example.com") o "*" para cualquiera. Con credenciales "*" no se acepta: cualquier sitio
+/// podría hacer requests con las cookies del usuario, hay que listar los orígenes.
+pub struct Cors {
+    origins: Vec<String>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    exposed_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub fn new() -> Cors {
+        Cors {
+            origins: Vec::new(),
+            methods: ["GET", "POST", "PUT", "PATCH", "DELETE"].iter().map(|m| m.to_string()).collect(),
+            headers: Vec::new(),
+            exposed_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    pub fn allow_origin(mut self, origin: &str) -> Cors {
+        self.origins.push(origin.trim_end_matches('/').to_string());
+        self
+    }
+
+    // Reemplaza los métodos permitidos (por defecto GET, POST, PUT, PATCH y DELETE)
+    pub fn allow_methods(mut self, methods: &[&str]) -> Cors {
+        self.methods = methods.iter().map(|method| method.to_ascii_uppercase()).collect();
+        self
+    }
+
+    // Headers que el cliente puede enviar; "*" acepta los que pida el preflight
+    pub fn allow_headers(mut self, headers: &[&str]) -> Cors {
+        self.headers = headers.iter().map(|header| header.to_string()).collect();
+        self
+    }
+
+    // Headers del response que el navegador deja leer al cliente
+    pub fn expose_headers(mut self, headers: &[&str]) -> Cors {
+        self.exposed_headers = headers.iter().map(|header| header.to_string()).collect();
+        self
+    }
+
+    // Permite enviar cookies y Authorization en requests entre orígenes (deja de aplicar el origen "*")
+    pub fn allow_credentials(mut self, credentials: bool) -> Cors {
+        self.credentials = credentials;
+        self
+    }
+
+    // Cuánto puede guardar el navegador la respuesta del preflight
+    pub fn max_age(mut self, max_age: Duration) -> Cors {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        self.origins.iter().filter(|pattern| !(self.credentials && *pattern == "*")).any(|pattern| matches_pattern(pattern, origin))
+    }
+
+    // Se acepta cualquier origen y se contesta "*": es el único caso en que el response no depende del Origin
+    fn any_origin(&self) -> bool {
+        !self.credentials && self.origins.iter().any(|pattern| pattern == "*")
+    }
+
+    // Valor de Access-Control-Allow-Origin. Con credenciales se devuelve el origen, que ya pasó por origin_allowed.
+    fn allow_origin_value(&self, origin: &str) -> String {
+        if self.any_origin() {
+            "*".to_string()
+        } else {
+            origin.to_string()
+        }
+    }
+
+    fn preflight(&self, request: &Request, origin: &str) -> Response {
+        let forbidden = || create_response(403, Some("[Error]: CORS request not allowed".to_string()), None::<HashMap<String, String>>);
+
+        let method = request.header("Access-Control-Request-Method").map(|method| method.trim().to_ascii_uppercase()).unwrap_or_default();
+        if !self.methods.contains(&method) {
+            return forbidden();
+        }
+
+        let requested_headers: Vec<String> = request
+            .header("Access-Control-Request-Headers")
+            .map(|headers| headers.split(',').map(|header| header.trim().to_string()).filter(|header| !header.is_empty()).collect())
+            .unwrap_or_default();
+        let any_header = self.headers.iter().any(|header| header == "*");
+        if !any_header && !requested_headers.iter().all(|requested| self.headers.iter().any(|header| header.eq_ignore_ascii_case(requested))) {
+            return forbidden();
+        }
+
+        let mut response = create_response(204, None, None::<HashMap<String, String>>);
+        self.add_origin_headers(&mut response, origin);
+        response.headers.insert("Access-Control-Allow-Methods".to_string(), self.methods.join(", "));
+        if !requested_headers.is_empty() {
+            let allowed = if any_header { requested_headers.join(", ") } else { self.headers.join(", ") };
+            response.headers.insert("Access-Control-Allow-Headers".to_string(), allowed);
+        }
+        if let Some(max_age) = self.max_age {
+            response.headers.insert("Access-Control-Max-Age".to_string(), max_age.as_secs().to_string());
+        }
+        add_vary(&mut response, "Access-Control-Request-Method, Access-Control-Request-Headers");
+        response
+    }
+
+    fn add_origin_headers(&self, response: &mut Response, origin: &str) {
+        response.headers.insert("Access-Control-Allow-Origin".to_string(), self.allow_origin_value(origin));
+        if self.credentials {
+            response.headers.insert("Access-Control-Allow-Credentials".to_string(), "true".to_string());
+        }
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Cors {
+        Cors::new()
+    }
+}
+
+// Compara un origen con un patrón donde '*' reemplaza cualquier texto
+fn matches_pattern(pattern: &str, origin: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !origin.starts_with(first) {
+        return false;
+    }
+    let mut rest = &origin[first.len()..];
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+// Agrega valores al header Vary sin borrar los que ya tenga
+fn add_vary(response: &mut Response, value: &str) {
+    let vary = match response.headers.get("Vary") {
+        Some(existing) if !existing.is_empty() => format!("{}, {}", existing, value),
+        _ => value.to_string(),
+    };
+    response.headers.insert("Vary".to_string(), vary);
+}
+
+impl Middleware for Cors {
+    fn handle(&self, request: Request, next: Next) -> Response {
+        let origin = match request.header("Origin") {
+            Some(origin) => origin.clone(),
+            None => return next.run(request),
+        };
+        let allowed = self.origin_allowed(&origin);
+
+        let mut response = if request.method == "OPTIONS" && request.header("Access-Control-Request-Method").is_some() {
+            if allowed {
+                self.preflight(&request, &origin)
+            } else {
+                create_response(403, Some("[Error]: CORS request not allowed".to_string()), None::<HashMap<String, String>>)
+            }
+        } else {
+            let mut response = next.run(request);
+            if allowed {
+                self.add_origin_headers(&mut response, &origin);
+                if !self.exposed_headers.is_empty() {
+                    response.headers.insert("Access-Control-Expose-Headers".to_string(), self.exposed_headers.join(", "));
+                }
+            }
+            response
+        };
+        // Permitido o no, el response depende del Origin: un cache compartido no lo debe servir a otro origen
+        if !self.any_origin() {
+            add_vary(&mut response, "Origin");
+        }
+        response
+    }
+}
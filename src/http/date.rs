@@ -0,0 +1,73 @@
+// Imports
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Fecha y hora en UTC separada en sus componentes
+struct DateTime {
+    year: i64,
+    month: usize,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    weekday: usize,
+}
+
+// Convierte un SystemTime a fecha civil (algoritmo days_from_civil de Howard Hinnant, al revés)
+fn to_datetime(time: SystemTime) -> DateTime {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    DateTime {
+        year,
+        month: (month - 1) as usize,
+        day,
+        hour: rem / 3600,
+        minute: rem % 3600 / 60,
+        second: rem % 60,
+        weekday: days.rem_euclid(7) as usize, // El 1 de enero de 1970 fue jueves
+    }
+}
+
+// Formato IMF-fixdate de HTTP, ej: "Sun, 06 Nov 1994 08:49:37 GMT"
+pub fn http_date(time: SystemTime) -> String {
+    let dt = to_datetime(time);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAYS[dt.weekday], dt.day, MONTHS[dt.month], dt.year, dt.hour, dt.minute, dt.second
+    )
+}
+
+// Formato de fecha de Common Log Format, ej: "10/Oct/2000:13:55:36 +0000"
+pub fn clf_date(time: SystemTime) -> String {
+    let dt = to_datetime(time);
+    format!(
+        "{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000",
+        dt.day, MONTHS[dt.month], dt.year, dt.hour, dt.minute, dt.second
+    )
+}
+
+// Formato RFC 3339 en UTC, ej: "2000-10-10T13:55:36Z"
+pub fn rfc3339(time: SystemTime) -> String {
+    let dt = to_datetime(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        dt.year, dt.month + 1, dt.day, dt.hour, dt.minute, dt.second
+    )
+}
@@ -0,0 +1,112 @@
+// Imports
+use std::{
+    net::SocketAddr,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+extern crate signal_hook;
+use self::signal_hook::iterator::Handle;
+
+use crate::http::connection::Listener;
+
+// Estado de arranque y apagado de un servidor, compartido entre los accept loops,
+// el thread de señales y el ServerHandle
+pub(crate) struct Lifecycle {
+    pub(crate) listeners: Vec<Listener>,
+    // El servidor deja de estar ready (se comparte con el endpoint de readiness)
+    pub(crate) shutting_down: Arc<AtomicBool>,
+    // Los accept loops terminan
+    pub(crate) stopping: AtomicBool,
+    running: Mutex<bool>,
+    started: Condvar,
+    signals: Mutex<Option<Handle>>,
+}
+
+impl Lifecycle {
+    pub(crate) fn new(listeners: Vec<Listener>) -> Lifecycle {
+        Lifecycle {
+            listeners,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            stopping: AtomicBool::new(false),
+            running: Mutex::new(false),
+            started: Condvar::new(),
+            signals: Mutex::new(None),
+        }
+    }
+
+    // Se llama cuando los accept loops ya están corriendo
+    pub(crate) fn set_running(&self) {
+        *self.running.lock().unwrap() = true;
+        self.started.notify_all();
+    }
+
+    // Guarda el handle de las señales para cerrar su thread al apagar
+    pub(crate) fn watch(&self, signals: Handle) {
+        *self.signals.lock().unwrap() = Some(signals);
+    }
+
+    // Deja de aceptar conexiones: los accept loops ven `stopping` la próxima vez que no tienen conexiones
+    // pendientes (como mucho ACCEPT_POLL después)
+    pub(crate) fn stop(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        if self.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Some(signals) = self.signals.lock().unwrap().take() {
+            signals.close();
+        }
+    }
+}
+
+/// Servidor corriendo en su propio thread (ver HttpServer::start).
+/// Al soltar el handle el servidor se apaga y se espera a que termine.
+pub struct ServerHandle {
+    local_addrs: Vec<SocketAddr>,
+    lifecycle: Arc<Lifecycle>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(local_addrs: Vec<SocketAddr>, lifecycle: Arc<Lifecycle>, thread: JoinHandle<()>) -> ServerHandle {
+        ServerHandle { local_addrs, lifecycle, thread: Some(thread) }
+    }
+
+    // Primera dirección TCP abierta (None si el servidor escucha en un Unix socket)
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addrs.first().copied()
+    }
+
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    // Espera hasta que el servidor esté aceptando conexiones, devuelve false si no lo logra en `timeout`
+    pub fn wait_ready(&self, timeout: Duration) -> bool {
+        let running = self.lifecycle.running.lock().unwrap();
+        let (running, _) = self.lifecycle.started.wait_timeout_while(running, timeout, |running| !*running).unwrap();
+        *running
+    }
+
+    // Deja de aceptar conexiones; los requests en curso terminan antes de que se apague el pool
+    pub fn shutdown(&self) {
+        self.lifecycle.stop();
+    }
+
+    // Espera a que el servidor termine (después de shutdown() o de una señal si hay graceful_shutdown)
+    pub fn join(mut self) -> thread::Result<()> {
+        match self.thread.take() {
+            Some(thread) => thread.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.lifecycle.stop();
+            let _ = thread.join();
+        }
+    }
+}
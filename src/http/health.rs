@@ -0,0 +1,79 @@
+// Imports
+use std::collections::HashMap;
+use std::sync::Arc;
+extern crate serde_json;
+
+use crate::http::parser::{create_response, Response};
+use crate::http::pool::PoolStats;
+
+/// Verificación de readiness: Err con el motivo si el servidor no debería recibir tráfico
+pub trait HealthCheck: Send + Sync {
+    fn check(&self) -> Result<(), String>;
+}
+
+impl<F> HealthCheck for F
+where
+    F: Fn() -> Result<(), String> + Send + Sync,
+{
+    fn check(&self) -> Result<(), String> {
+        self()
+    }
+}
+
+/// Falla cuando la cola del pool tiene más de `max_queue` jobs esperando
+pub struct PoolCheck {
+    stats: Arc<PoolStats>,
+    max_queue: usize,
+}
+
+impl PoolCheck {
+    pub fn new(stats: Arc<PoolStats>, max_queue: usize) -> PoolCheck {
+        PoolCheck { stats, max_queue }
+    }
+}
+
+impl HealthCheck for PoolCheck {
+    fn check(&self) -> Result<(), String> {
+        let queued = self.stats.queue_depth();
+        if queued > self.max_queue {
+            return Err(format!("{} jobs queued ({} busy of {} workers)", queued, self.stats.busy_workers(), self.stats.workers()));
+        }
+        Ok(())
+    }
+}
+
+fn json_response(status_code: u16, body: serde_json::Value) -> Response {
+    let mut response = create_response(status_code, Some(body.to_string()), None::<HashMap<String, String>>);
+    response.headers.insert("Content-Type".to_string(), "application/json".to_string());
+    response.headers.insert("Cache-Control".to_string(), "no-store".to_string());
+    response
+}
+
+// Liveness: si el servidor puede contestar, está vivo
+pub fn liveness() -> Response {
+    json_response(200, serde_json::json!({ "status": "ok" }))
+}
+
+// Readiness: 200 si todos los checks pasan y el servidor no se está apagando, si no 503.
+// El body incluye el resultado de cada check.
+pub fn readiness(checks: &[(String, Arc<dyn HealthCheck>)], shutting_down: bool) -> Response {
+    let mut ready = !shutting_down;
+    let mut results = serde_json::Map::new();
+    for (name, check) in checks {
+        let result = match check.check() {
+            Ok(_) => serde_json::json!({ "status": "ok" }),
+            Err(error) => {
+                ready = false;
+                serde_json::json!({ "status": "failing", "error": error })
+            }
+        };
+        results.insert(name.clone(), result);
+    }
+
+    let body = serde_json::json!({
+        "status": if ready { "ready" } else { "not ready" },
+        "shutting_down": shutting_down,
+        "checks": results,
+    });
+    json_response(if ready { 200 } else { 503 }, body)
+}
@@ -0,0 +1,153 @@
+// Imports
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+extern crate base64;
+extern crate hmac;
+extern crate rsa;
+extern crate serde;
+extern crate serde_json;
+extern crate sha2;
+use self::base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use self::hmac::{Hmac, Mac};
+use self::rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use self::rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use self::rsa::signature::{SignatureEncoding, Signer, Verifier};
+use self::rsa::{RsaPrivateKey, RsaPublicKey};
+use self::serde::{Deserialize, Serialize};
+use self::sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Tolerancia para diferencias de reloj al validar exp
+const LEEWAY_SECS: u64 = 30;
+
+/// Claims de los tokens que emite el servidor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub iat: u64,
+    pub exp: u64,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// Errores al validar un token
+#[derive(Debug, PartialEq)]
+pub enum JwtError {
+    Malformed,
+    UnsupportedAlgorithm,
+    InvalidSignature,
+    Expired,
+}
+
+// Llave para firmar y verificar (las llaves RSA van en Box porque son mucho más grandes)
+enum JwtKey {
+    Hs256(Vec<u8>),
+    Rs256 { signing: Option<Box<SigningKey<Sha256>>>, verifying: Box<VerifyingKey<Sha256>> },
+}
+
+/// Emisor y verificador de JWT (HS256 o RS256)
+pub struct Jwt {
+    key: JwtKey,
+}
+
+// Segundos desde epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl Jwt {
+    // HS256 con un secreto compartido
+    pub fn hs256(secret: &[u8]) -> Jwt {
+        Jwt { key: JwtKey::Hs256(secret.to_vec()) }
+    }
+
+    // RS256 con una llave privada PKCS#8 en PEM (puede firmar y verificar)
+    pub fn rs256_private_pem(pem: &str) -> Result<Jwt, String> {
+        let private = RsaPrivateKey::from_pkcs8_pem(pem).map_err(|e| format!("[Error]: Invalid RSA private key: {}", e))?;
+        let verifying = Box::new(VerifyingKey::<Sha256>::new(private.to_public_key()));
+        Ok(Jwt { key: JwtKey::Rs256 { signing: Some(Box::new(SigningKey::<Sha256>::new(private))), verifying } })
+    }
+
+    // RS256 con solo la llave pública en PEM (solo puede verificar)
+    pub fn rs256_public_pem(pem: &str) -> Result<Jwt, String> {
+        let public = RsaPublicKey::from_public_key_pem(pem).map_err(|e| format!("[Error]: Invalid RSA public key: {}", e))?;
+        Ok(Jwt { key: JwtKey::Rs256 { signing: None, verifying: Box::new(VerifyingKey::<Sha256>::new(public)) } })
+    }
+
+    fn algorithm(&self) -> &'static str {
+        match self.key {
+            JwtKey::Hs256(_) => "HS256",
+            JwtKey::Rs256 { .. } => "RS256",
+        }
+    }
+
+    // Genera un token firmado con los claims
+    pub fn encode(&self, claims: &Claims) -> Result<String, String> {
+        let header = serde_json::json!({ "alg": self.algorithm(), "typ": "JWT" });
+        let payload = serde_json::to_vec(claims).map_err(|e| e.to_string())?;
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+
+        let signature = match self.key {
+            JwtKey::Hs256(ref secret) => {
+                let mut mac = <HmacSha256 as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+                mac.update(signing_input.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+            JwtKey::Rs256 { signing: Some(ref key), .. } => key.sign(signing_input.as_bytes()).to_vec(),
+            JwtKey::Rs256 { signing: None, .. } => return Err("[Error]: RS256 public key cannot sign tokens".to_string()),
+        };
+
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    // Valida la firma y la expiración de un token y devuelve sus claims
+    pub fn decode(&self, token: &str) -> Result<Claims, JwtError> {
+        let mut parts = token.split('.');
+        let (header, payload, signature) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(payload), Some(signature), None) => (header, payload, signature),
+            _ => return Err(JwtError::Malformed),
+        };
+
+        // El algoritmo debe ser exactamente el de la llave configurada (evita "none" y confusión HS/RS)
+        let header_json: serde_json::Value = URL_SAFE_NO_PAD
+            .decode(header)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(JwtError::Malformed)?;
+        if header_json["alg"].as_str() != Some(self.algorithm()) {
+            return Err(JwtError::UnsupportedAlgorithm);
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| JwtError::Malformed)?;
+        let signing_input = &token[..header.len() + 1 + payload.len()];
+        let valid = match self.key {
+            JwtKey::Hs256(ref secret) => {
+                let mut mac = <HmacSha256 as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+                mac.update(signing_input.as_bytes());
+                mac.verify_slice(&signature).is_ok()
+            }
+            JwtKey::Rs256 { ref verifying, .. } => Signature::try_from(signature.as_slice())
+                .map(|signature| verifying.verify(signing_input.as_bytes(), &signature).is_ok())
+                .unwrap_or(false),
+        };
+        if !valid {
+            return Err(JwtError::InvalidSignature);
+        }
+
+        let claims: Claims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(JwtError::Malformed)?;
+        if claims.exp + LEEWAY_SECS < unix_now() {
+            return Err(JwtError::Expired);
+        }
+
+        Ok(claims)
+    }
+}
@@ -0,0 +1,167 @@
+// Imports
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Mutex},
+    time::Duration,
+};
+
+use crate::http::pool::PoolStats;
+
+// Límites de los buckets del histograma de latencia (segundos)
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// Métodos que se usan como label; cualquier otro se cuenta como "OTHER" para no crear series sin límite
+const KNOWN_METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS", "HEAD"];
+
+// Contadores atómicos, se puede observar sin lock desde varios threads (ej: cada worker del pool).
+// Cada observación cuenta solo en su bucket (el último es +Inf), los acumulados se calculan al renderizar.
+#[derive(Default)]
+pub(crate) struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    pub(crate) fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS.iter().position(|limit| seconds <= *limit).unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Métricas del servidor en formato de texto de Prometheus
+#[derive(Default)]
+pub struct Metrics {
+    // (method, route, status) -> requests
+    requests: Mutex<HashMap<(String, String, u16), u64>>,
+    // (method, route) -> latencias
+    latencies: Mutex<HashMap<(String, String), Histogram>>,
+    in_flight: AtomicUsize,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    connections_accepted: AtomicU64,
+    connections_failed: AtomicU64,
+}
+
+/// Cuenta un request en curso mientras exista
+pub struct InFlight<'a> {
+    metrics: &'a Metrics,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Label del método, acotado a los métodos conocidos
+pub fn method_label(method: &str) -> &str {
+    if KNOWN_METHODS.contains(&method) { method } else { "OTHER" }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    pub fn in_flight(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight { metrics: self }
+    }
+
+    pub fn connection_accepted(&self) {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_failed(&self) {
+        self.connections_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn bytes_in(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn bytes_out(&self, bytes: usize) {
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    // Registra un request respondido. `route` debe ser la ruta registrada (no el path del cliente).
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let method = method_label(method).to_string();
+        *self.requests.lock().unwrap().entry((method.clone(), route.to_string(), status)).or_insert(0) += 1;
+        self.latencies.lock().unwrap().entry((method, route.to_string())).or_default().observe(latency);
+    }
+
+    // Texto para el endpoint /metrics
+    pub fn render(&self, pool: &PoolStats) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "httprust_http_requests_total", "counter", "HTTP requests by method, route and status.");
+        let requests = self.requests.lock().unwrap();
+        let mut keys: Vec<&(String, String, u16)> = requests.keys().collect();
+        keys.sort();
+        for key in keys {
+            let (method, route, status) = key;
+            let _ = writeln!(out, "httprust_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}", escape(method), escape(route), status, requests[key]);
+        }
+        drop(requests);
+
+        header(&mut out, "httprust_http_request_duration_seconds", "histogram", "HTTP request latency by method and route.");
+        let latencies = self.latencies.lock().unwrap();
+        let mut keys: Vec<&(String, String)> = latencies.keys().collect();
+        keys.sort();
+        for key in keys {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape(&key.0), escape(&key.1));
+            histogram(&mut out, "httprust_http_request_duration_seconds", &labels, &latencies[key]);
+        }
+        drop(latencies);
+
+        header(&mut out, "httprust_pool_queue_wait_seconds", "histogram", "Time jobs wait in the pool queue before a worker takes them.");
+        histogram(&mut out, "httprust_pool_queue_wait_seconds", "", pool.queue_wait());
+
+        let values: [(&str, &str, &str, u64); 9] = [
+            ("httprust_http_requests_in_flight", "gauge", "HTTP requests being handled.", self.in_flight.load(Ordering::Relaxed) as u64),
+            ("httprust_http_request_bytes_total", "counter", "Bytes received in HTTP requests.", self.bytes_in.load(Ordering::Relaxed)),
+            ("httprust_http_response_bytes_total", "counter", "Bytes sent in HTTP responses.", self.bytes_out.load(Ordering::Relaxed)),
+            ("httprust_connections_accepted_total", "counter", "Connections accepted by the listener.", self.connections_accepted.load(Ordering::Relaxed)),
+            ("httprust_connections_failed_total", "counter", "Connections the listener failed to accept.", self.connections_failed.load(Ordering::Relaxed)),
+            ("httprust_pool_workers", "gauge", "Worker threads in the pool.", pool.workers() as u64),
+            ("httprust_pool_busy_workers", "gauge", "Worker threads running a job.", pool.busy_workers() as u64),
+            ("httprust_pool_queue_depth", "gauge", "Jobs waiting for a worker.", pool.queue_depth() as u64),
+            ("httprust_pool_rejected_total", "counter", "Jobs discarded because the pool queue was full.", pool.rejected()),
+        ];
+        for (name, kind, help, value) in values.iter() {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Series de un histograma (labels puede estar vacío)
+fn histogram(out: &mut String, name: &str, labels: &str, histogram: &Histogram) {
+    let separator = if labels.is_empty() { "" } else { "," };
+    let mut count = 0;
+    for (limit, bucket) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+        count += bucket.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, limit, count);
+    }
+    count += histogram.buckets[LATENCY_BUCKETS.len()].load(Ordering::Relaxed);
+    let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, separator, count);
+    let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+    let _ = writeln!(out, "{}_sum{} {}", name, labels, histogram.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9);
+    let _ = writeln!(out, "{}_count{} {}", name, labels, count);
+}
+
+// Escapa un valor de label (barras, comillas y saltos de línea)
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
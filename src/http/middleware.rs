@@ -0,0 +1,30 @@
+// Imports
+use std::sync::Arc;
+
+use crate::http::parser::{Request, Response};
+
+/// Middleware que envuelve el manejo de un request.
+/// Puede modificar el request, responder sin llamar al resto de la cadena o modificar el response.
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: Request, next: Next) -> Response;
+}
+
+/// Resto de la cadena de middlewares, termina en el controller de la ruta
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    endpoint: &'a dyn Fn(Request) -> Response,
+}
+
+impl<'a> Next<'a> {
+    pub fn new(middlewares: &'a [Arc<dyn Middleware>], endpoint: &'a dyn Fn(Request) -> Response) -> Next<'a> {
+        Next { middlewares, endpoint }
+    }
+
+    // Pasa el request al siguiente middleware (o al controller si ya no quedan)
+    pub fn run(self, request: Request) -> Response {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next { middlewares: rest, endpoint: self.endpoint }),
+            None => (self.endpoint)(request),
+        }
+    }
+}
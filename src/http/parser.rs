@@ -1,6 +1,14 @@
 // Imports
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
 extern crate serde_json;
+extern crate tracing;
+use self::tracing::warn;
+
+use crate::http::auth::Identity;
+use crate::http::date::http_date;
+use crate::http::session::Session;
 
 // Struct de Request
 #[derive(Debug)]
@@ -11,6 +19,9 @@ pub struct Request {
     pub body: Option<Body>,
     pub params: HashMap<String, String>,
     pub cookies: HashMap<String, String>,
+    pub session: Session,
+    pub identity: Option<Identity>,
+    pub remote_addr: Option<SocketAddr>,
 }
 
 // Struct de Response
@@ -19,7 +30,34 @@ pub struct Response {
     pub status_code: u16,
     pub headers: HashMap<String, String>,
     pub body: Option<String>,
-    pub cookies: Option<HashMap<String, String>>,
+    pub cookies: CookieJar,
+}
+
+impl Request {
+    // Busca un header sin importar mayúsculas/minúsculas
+    pub fn header(&self, name: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    // Sesión del cliente (vacía si no se usa el SessionMiddleware)
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl Response {
+    // Agrega una cookie al response (reemplaza una con el mismo nombre, path y domain)
+    pub fn add_cookie(&mut self, cookie: Cookie) {
+        self.cookies.add(cookie);
+    }
+
+    // Le pide al cliente borrar una cookie. Debe llevar el mismo Path y Domain con que se creó.
+    pub fn remove_cookie(&mut self, cookie: Cookie) {
+        self.cookies.remove(cookie);
+    }
 }
 
 /// Enum para manejar tipos del body
@@ -29,6 +67,230 @@ pub enum Body {
     Json(serde_json::Value),
 }
 
+/// Valores del atributo SameSite
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// Cookie para enviar en Set-Cookie, con los atributos de RFC 6265
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    pub max_age: Option<i64>,
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    // Constructor, los atributos se agregan con los métodos de abajo
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Cookie {
+        self.path = Some(path.to_string());
+        self
+    }
+    pub fn domain(mut self, domain: &str) -> Cookie {
+        self.domain = Some(domain.to_string());
+        self
+    }
+    pub fn expires(mut self, expires: SystemTime) -> Cookie {
+        self.expires = Some(expires);
+        self
+    }
+    pub fn max_age(mut self, seconds: i64) -> Cookie {
+        self.max_age = Some(seconds);
+        self
+    }
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+        self
+    }
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+        self
+    }
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    // Convierte la cookie en una que el navegador borra de inmediato
+    fn into_removal(mut self) -> Cookie {
+        self.value = String::new();
+        self.max_age = Some(0);
+        self.expires = Some(UNIX_EPOCH);
+        self
+    }
+
+    // Valida la cookie y genera el valor del header Set-Cookie
+    pub fn to_header_value(&self) -> Result<String, String> {
+        if self.name.is_empty() || !self.name.bytes().all(is_token_char) {
+            return Err(format!("[Error]: Invalid cookie name '{}'", self.name));
+        }
+        // Prefijos de RFC 6265bis que el navegador exige
+        if self.name.starts_with("__Secure-") && !self.secure {
+            return Err(format!("[Error]: Cookie '{}' requires the Secure attribute", self.name));
+        }
+        if self.name.starts_with("__Host-") && (!self.secure || self.domain.is_some() || self.path.as_deref() != Some("/")) {
+            return Err(format!("[Error]: Cookie '{}' requires Secure, Path=/ and no Domain", self.name));
+        }
+        if self.same_site == Some(SameSite::None) && !self.secure {
+            return Err(format!("[Error]: Cookie '{}' with SameSite=None requires the Secure attribute", self.name));
+        }
+
+        let mut header = format!("{}={}", self.name, encode_cookie_value(&self.value));
+
+        if let Some(ref domain) = self.domain {
+            let domain = domain.trim_start_matches('.');
+            if domain.is_empty() || !domain.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.') {
+                return Err(format!("[Error]: Invalid cookie domain '{}'", domain));
+            }
+            header.push_str(&format!("; Domain={}", domain));
+        }
+        if let Some(ref path) = self.path {
+            if !path.starts_with('/') || path.bytes().any(|b| b.is_ascii_control() || b == b';') {
+                return Err(format!("[Error]: Invalid cookie path '{}'", path));
+            }
+            header.push_str(&format!("; Path={}", path));
+        }
+        if let Some(expires) = self.expires {
+            header.push_str(&format!("; Expires={}", http_date(expires)));
+        }
+        if let Some(max_age) = self.max_age {
+            header.push_str(&format!("; Max-Age={}", max_age.max(0)));
+        }
+        if self.secure {
+            header.push_str("; Secure");
+        }
+        if self.http_only {
+            header.push_str("; HttpOnly");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => header.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => header.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => header.push_str("; SameSite=None"),
+            None => {}
+        }
+
+        Ok(header)
+    }
+}
+
+/// Colección de cookies que se envían en un response
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> CookieJar {
+        CookieJar { cookies: Vec::new() }
+    }
+
+    // Agrega una cookie, reemplazando la que tenga el mismo nombre, path y domain
+    pub fn add(&mut self, cookie: Cookie) {
+        self.cookies.retain(|c| !(c.name == cookie.name && c.path == cookie.path && c.domain == cookie.domain));
+        self.cookies.push(cookie);
+    }
+
+    // Agrega una cookie de borrado
+    pub fn remove(&mut self, cookie: Cookie) {
+        self.add(cookie.into_removal());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        self.cookies.iter().find(|c| c.name == name)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Cookie> {
+        self.cookies.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Cookie> {
+        self.cookies.iter_mut()
+    }
+}
+
+// Caracteres permitidos en un token (nombre de cookie) según RFC 7230
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+// Caracteres permitidos en el valor de una cookie (cookie-octet de RFC 6265)
+fn is_cookie_octet(b: u8) -> bool {
+    b == 0x21 || (0x23..=0x2B).contains(&b) || (0x2D..=0x3A).contains(&b) || (0x3C..=0x5B).contains(&b) || (0x5D..=0x7E).contains(&b)
+}
+
+// Codifica el valor de una cookie: los valores válidos (o ya entre comillas) se dejan igual,
+// el resto de bytes (incluyendo '%') se codifica con porcentajes
+fn encode_cookie_value(value: &str) -> String {
+    let inner = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+    if let Some(inner) = inner {
+        if inner.bytes().all(|b| is_cookie_octet(b) && b != b'%') {
+            return value.to_string();
+        }
+    }
+    if value.bytes().all(|b| is_cookie_octet(b) && b != b'%') {
+        return value.to_string();
+    }
+
+    value
+        .bytes()
+        .map(|b| {
+            if is_cookie_octet(b) && b != b'%' {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+// Decodifica el valor de una cookie recibida: quita comillas y decodifica porcentajes
+fn decode_cookie_value(value: &str) -> String {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
 // Parser: convertirte un request HTTP en un objeto Request
 pub fn parse_request(request: &str) -> Result<Request, String> {
     let mut lines = request.lines();
@@ -107,7 +369,7 @@ pub fn parse_request(request: &str) -> Result<Request, String> {
         for cookie_str in cookies_str.split(';') {
             let cookie_str = cookie_str.trim();
             if let Some((key, value)) = cookie_str.split_once('=') {
-                cookies.insert(key.to_string(), value.to_string());
+                cookies.insert(key.to_string(), decode_cookie_value(value));
             }
         }
     }
@@ -119,6 +381,9 @@ pub fn parse_request(request: &str) -> Result<Request, String> {
         body,
         params,
         cookies,
+        session: Session::default(),
+        identity: None,
+        remote_addr: None,
     })
 }
 
@@ -129,43 +394,75 @@ pub fn create_response(status_code: u16, body: Option<String>, cookies: Option<H
         headers.insert("Content-Length".to_string(), body.len().to_string());
     }
 
+    // Las cookies simples (key=value) se envían sin atributos
+    let mut jar = CookieJar::new();
+    if let Some(cookies) = cookies {
+        for (key, value) in cookies.iter() {
+            jar.add(Cookie::new(key, value));
+        }
+    }
+
     Response {
         status_code,
         headers,
         body,
-        cookies,
+        cookies: jar,
+    }
+}
+
+// Frase de estado para cada código de respuesta
+pub fn reason_phrase(status_code: u16) -> &'static str {
+    match status_code {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
     }
 }
 
 /// Formatea un Respone en un string para enviar
-fn format_response(response: &Response) -> String {
-    let status_line = match response.status_code {
-        200 => "HTTP/1.1 200 OK",
-        404 => "HTTP/1.1 404 NOT FOUND",
-        400 => "HTTP/1.1 400 BAD REQUEST",
-        _ => "HTTP/1.1 500 INTERNAL SERVER ERROR",
-    };
+pub fn format_response(response: &Response) -> String {
+    let mut head = format_head(response);
 
-    let mut headers = response
-        .headers
-        .iter()
-        .map(|(k, v)| format!("{}: {}", k, v))
-        .collect::<Vec<String>>()
-        .join("\r\n");
-    
-    // Add cookies
-    if let Some(cookies) = &response.cookies {
-        for (key, value) in cookies.iter() {
-            let cookie_str = format!("Set-Cookie: {}={}\r\n", key, value);
-            headers.push_str(&cookie_str);
-        } 
+    // Las respuestas 1xx y 204 no llevan body
+    if response.status_code >= 200 && response.status_code != 204 {
+        let length = response.body.as_ref().map(|b| b.len()).unwrap_or(0);
+        head.push_str(&format!("Content-Length: {}\r\n", length));
     }
-    
 
-    format!(
-        "{}\r\n{}\r\n\r\n{}",
-        status_line,
-        headers,
-        response.body.clone().unwrap_or_default()
-    )
-}
\ No newline at end of file
+    format!("{}\r\n{}", head, response.body.clone().unwrap_or_default())
+}
+
+// Línea de estado, headers y cookies, sin Content-Length ni la línea vacía del final
+// (los event streams no tienen un largo conocido)
+pub fn format_head(response: &Response) -> String {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status_code, reason_phrase(response.status_code));
+
+    for (key, value) in response.headers.iter() {
+        // Content-Length lo agrega format_response a partir del body
+        if !key.eq_ignore_ascii_case("Content-Length") {
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+    }
+
+    // Add cookies (las cookies inválidas se descartan)
+    for cookie in response.cookies.iter() {
+        match cookie.to_header_value() {
+            Ok(value) => head.push_str(&format!("Set-Cookie: {}\r\n", value)),
+            Err(e) => warn!("Dropped invalid cookie: {}", e),
+        }
+    }
+
+    head
+}
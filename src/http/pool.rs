@@ -1,56 +1,419 @@
 use std::{
-    sync::{mpsc, Arc, Mutex},
-    thread
+    any::Any,
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, Condvar, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
 };
+extern crate crossbeam_channel;
+extern crate tracing;
+use self::crossbeam_channel::{unbounded, Receiver, Sender};
+use self::tracing::{debug, error, info_span};
+
+use crate::http::metrics::Histogram;
+
+// Tiempo que un worker por encima del mínimo espera un job antes de terminar
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(60);
 
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>,
+    shared: Arc<Shared>,
+    sender: Option<Sender<Task>>,
+}
+
+// Lo que comparten los workers (y los que se crean para reemplazar a uno que murió)
+struct Shared {
+    workers: Mutex<Vec<Worker>>,
+    queue: Queue,
+    stats: Arc<PoolStats>,
+    min: usize,
+    max: usize,
+    keep_alive: Duration,
+    name: String,
+    stack_size: Option<usize>,
+    next_id: AtomicUsize,
+}
+
+/// Configuración del pool: ThreadPool::builder().min(2).max(16).keep_alive(..).build()
+pub struct PoolBuilder {
+    min: usize,
+    max: usize,
+    keep_alive: Duration,
+    name: String,
+    stack_size: Option<usize>,
+}
+
+impl PoolBuilder {
+    // Workers que siempre están corriendo
+    pub fn min(mut self, min: usize) -> PoolBuilder {
+        self.min = min;
+        self
+    }
+
+    // Límite de workers cuando se acumulan jobs en la cola
+    pub fn max(mut self, max: usize) -> PoolBuilder {
+        self.max = max;
+        self
+    }
+
+    // Tiempo sin jobs después del cual terminan los workers que sobran del mínimo
+    pub fn keep_alive(mut self, keep_alive: Duration) -> PoolBuilder {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    // Prefijo del nombre de los threads ("worker" queda como "worker-0", "worker-1", ...)
+    pub fn name(mut self, name: &str) -> PoolBuilder {
+        self.name = name.to_string();
+        self
+    }
+
+    // Tamaño del stack de cada worker en bytes (por defecto el de std)
+    pub fn stack_size(mut self, bytes: usize) -> PoolBuilder {
+        self.stack_size = Some(bytes);
+        self
+    }
+
+    pub fn build(self) -> ThreadPool {
+        assert!(self.max > 0 && self.min <= self.max);
+
+        let (sender, receiver) = unbounded();
+        let shared = Arc::new(Shared {
+            workers: Mutex::new(Vec::with_capacity(self.max)),
+            queue: Queue::new(receiver),
+            stats: Arc::new(PoolStats::default()),
+            min: self.min,
+            max: self.max,
+            keep_alive: self.keep_alive,
+            name: self.name,
+            stack_size: self.stack_size,
+            next_id: AtomicUsize::new(0),
+        });
+
+        let pool = ThreadPool { shared, sender: Some(sender) };
+        for _ in 0..pool.shared.min {
+            pool.grow();
+        }
+        pool
+    }
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
+// Un job en la cola, con lo que se hace si se descarta por sobrecarga
+struct Task {
+    job: Job,
+    rejected: Option<Job>,
+    queued_at: Instant,
+}
+
+/// Qué hacer con un job nuevo cuando la cola está llena
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overload {
+    // Se espera a que haya lugar (el accept se detiene y las conexiones esperan en el backlog del sistema)
+    Block,
+    // Se descarta el job nuevo
+    Reject,
+    // Se descarta el job que más tiempo lleva esperando y se encola el nuevo
+    DropOldest,
+}
+
+impl Overload {
+    pub fn parse(policy: &str) -> Option<Overload> {
+        match policy.to_ascii_lowercase().replace('_', "-").as_str() {
+            "block" => Some(Overload::Block),
+            "reject" => Some(Overload::Reject),
+            "drop-oldest" => Some(Overload::DropOldest),
+            _ => None,
+        }
+    }
+}
+
+// Cola de jobs sobre un canal MPMC de crossbeam: encolar y sacar un job no pasa por un Mutex que
+// compartan todos los workers. Sin límite hasta que se llama a set_queue_limit; el límite se controla
+// reservando un lugar en stats.queued antes de mandar el job.
+// El sender lo tiene el ThreadPool, al soltarlo los workers terminan lo que queda en la cola y salen.
+// Los locks del pool se recuperan aunque un panic los haya envenenado: si no, cada worker nuevo
+// haría panic al tomar uno y se reemplazaría a sí mismo sin fin.
+struct Queue {
+    receiver: Receiver<Task>,
+    // usize::MAX mientras no hay límite
+    capacity: AtomicUsize,
+    overload: Mutex<Overload>,
+    // Workers esperando un job (o recién creados, que van a esperar uno)
+    waiting: AtomicUsize,
+    // Productores esperando lugar con Overload::Block (solo con la cola llena)
+    blocked: AtomicUsize,
+    space_lock: Mutex<()>,
+    space: Condvar,
+}
+
+enum Pop {
+    Task(Task),
+    Idle,
+    Closed,
+}
+
+impl Queue {
+    fn new(receiver: Receiver<Task>) -> Queue {
+        Queue {
+            receiver,
+            capacity: AtomicUsize::new(usize::MAX),
+            overload: Mutex::new(Overload::Block),
+            waiting: AtomicUsize::new(0),
+            blocked: AtomicUsize::new(0),
+            space_lock: Mutex::new(()),
+            space: Condvar::new(),
+        }
+    }
+
+    fn set_limit(&self, capacity: usize, overload: Overload) {
+        *self.overload.lock().unwrap_or_else(PoisonError::into_inner) = overload;
+        self.capacity.store(capacity, Ordering::SeqCst);
+    }
+
+    // Encola el task, devuelve el task que se descartó si la cola estaba llena
+    fn push(&self, sender: &Sender<Task>, task: Task, stats: &PoolStats) -> Option<Task> {
+        let mut discarded = None;
+        if !self.reserve(stats) {
+            let overload = *self.overload.lock().unwrap_or_else(PoisonError::into_inner);
+            match overload {
+                Overload::Block => self.wait_for_space(stats),
+                Overload::Reject => return Some(task),
+                Overload::DropOldest => match self.receiver.try_recv() {
+                    // El job nuevo ocupa el lugar del descartado
+                    Ok(oldest) => discarded = Some(oldest),
+                    // Los workers vaciaron la cola mientras tanto
+                    Err(_) => {
+                        stats.queued.fetch_add(1, Ordering::SeqCst);
+                    }
+                },
+            }
+        }
+        // No falla: el receiver vive en la cola mientras exista el pool
+        let _ = sender.send(task);
+        discarded
+    }
+
+    // Reserva un lugar para un job si la cola no está llena
+    fn reserve(&self, stats: &PoolStats) -> bool {
+        let capacity = self.capacity.load(Ordering::SeqCst);
+        if capacity == usize::MAX {
+            stats.queued.fetch_add(1, Ordering::SeqCst);
+            return true;
+        }
+        stats.queued.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| if queued < capacity { Some(queued + 1) } else { None }).is_ok()
+    }
+
+    // Espera hasta reservar un lugar. Se cuenta en blocked antes de intentar: un worker que saca un job
+    // después de ese intento ve blocked > 0 y avisa con space_lock tomado, así el aviso no se pierde.
+    fn wait_for_space(&self, stats: &PoolStats) {
+        self.blocked.fetch_add(1, Ordering::SeqCst);
+        let mut guard = self.space_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        while !self.reserve(stats) {
+            guard = self.space.wait(guard).unwrap_or_else(PoisonError::into_inner);
+        }
+        drop(guard);
+        self.blocked.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Espera el siguiente task. Con keep_alive devuelve Idle si no llega ninguno en ese tiempo.
+    // `counted` indica que el worker ya se contó en waiting (los workers nuevos se cuentan al crearlos).
+    fn pop(&self, stats: &PoolStats, keep_alive: Option<Duration>, counted: bool) -> Pop {
+        if !counted {
+            self.waiting.fetch_add(1, Ordering::SeqCst);
+        }
+        let received = match keep_alive {
+            Some(keep_alive) => self.receiver.recv_timeout(keep_alive).map_err(|e| e.is_timeout()),
+            None => self.receiver.recv().map_err(|_| false),
+        };
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+
+        match received {
+            Ok(task) => {
+                stats.queued.fetch_sub(1, Ordering::SeqCst);
+                if self.blocked.load(Ordering::SeqCst) > 0 {
+                    let _guard = self.space_lock.lock().unwrap_or_else(PoisonError::into_inner);
+                    self.space.notify_one();
+                }
+                Pop::Task(task)
+            }
+            Err(true) => Pop::Idle,
+            // Se soltó el sender y la cola está vacía
+            Err(false) => Pop::Closed,
+        }
+    }
+
+    // Hay más jobs que workers libres para tomarlos (contando el que se está por encolar)
+    fn needs_worker(&self, stats: &PoolStats) -> bool {
+        stats.queued.load(Ordering::SeqCst) >= self.waiting.load(Ordering::SeqCst)
+    }
+
+    // Incluye los jobs con lugar reservado que todavía no llegaron al canal
+    fn is_empty(&self, stats: &PoolStats) -> bool {
+        stats.queued.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// Contadores del pool (para métricas)
+#[derive(Default)]
+pub struct PoolStats {
+    workers: AtomicUsize,
+    busy: AtomicUsize,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+    queue_wait: Histogram,
+}
+
+impl PoolStats {
+    pub fn workers(&self) -> usize {
+        self.workers.load(Ordering::Relaxed)
+    }
+
+    // Workers ejecutando un job
+    pub fn busy_workers(&self) -> usize {
+        self.busy.load(Ordering::Relaxed)
+    }
+
+    // Workers libres esperando un job
+    pub fn idle_workers(&self) -> usize {
+        self.workers().saturating_sub(self.busy_workers())
+    }
+
+    // Jobs esperando a un worker
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    // Jobs descartados porque la cola estaba llena
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    // Histograma del tiempo que esperan los jobs en la cola
+    pub(crate) fn queue_wait(&self) -> &Histogram {
+        &self.queue_wait
+    }
+}
+
+// Mensaje de un panic (los panics con format! llevan un String, los literales un &str)
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
 impl ThreadPool {
     // Function that creates ThreadPool
-    // Size = number of threads in the pool
+    // Size = number of threads in the pool (tamaño fijo, ver builder para un pool elástico)
     pub fn new(size: usize) -> ThreadPool {
         assert!(size > 0);
+        ThreadPool::builder().min(size).max(size).build()
+    }
 
-        let (sender, receiver) = mpsc::channel();
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder { min: 1, max: 1, keep_alive: DEFAULT_KEEP_ALIVE, name: "worker".to_string(), stack_size: None }
+    }
 
-        let receiver = Arc::new(Mutex::new(receiver));
-        
-        let mut workers = Vec::with_capacity(size);
+    // Workers corriendo en este momento
+    pub fn size(&self) -> usize {
+        self.shared.stats.workers()
+    }
 
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
-        }
+    // Límites (min, max) de workers
+    pub fn bounds(&self) -> (usize, usize) {
+        (self.shared.min, self.shared.max)
+    }
 
-        ThreadPool {
-            workers,
-            sender: Some(sender),
-        }
+    // Limita la cola a `capacity` jobs esperando; `overload` indica qué hacer cuando está llena
+    pub fn set_queue_limit(&self, capacity: usize, overload: Overload) {
+        self.shared.queue.set_limit(capacity, overload);
+    }
+
+    pub fn stats(&self) -> Arc<PoolStats> {
+        Arc::clone(&self.shared.stats)
     }
 
     pub fn execute<F>(&self, f: F)
     where
         F: FnOnce() + Send + 'static,
     {
-        let job = Box::new(f);
+        self.submit(Box::new(f), None);
+    }
+
+    // Igual que execute, pero si el job se descarta por sobrecarga se llama a `rejected`
+    // (en el thread que encola, no en un worker)
+    pub fn execute_or<F, R>(&self, f: F, rejected: R)
+    where
+        F: FnOnce() + Send + 'static,
+        R: FnOnce() + Send + 'static,
+    {
+        self.submit(Box::new(f), Some(Box::new(rejected)));
+    }
+
+    fn submit(&self, job: Job, rejected: Option<Job>) {
+        // El pool crece antes de encolar, así un job no espera (ni se rechaza) si todavía se puede crear un worker
+        if self.shared.queue.needs_worker(&self.shared.stats) {
+            self.grow();
+        }
+        let task = Task { job, rejected, queued_at: Instant::now() };
+        let discarded = self.shared.queue.push(self.sender.as_ref().unwrap(), task, &self.shared.stats);
+        // El último worker pudo terminar por inactividad después del chequeo de arriba (con min == 0):
+        // el lugar del job ya está reservado en stats.queued, así que o el worker lo ve y no termina o acá
+        // se ve el pool vacío
+        if self.shared.stats.workers.load(Ordering::SeqCst) == 0 {
+            self.grow();
+        }
+        if let Some(discarded) = discarded {
+            self.shared.stats.rejected.fetch_add(1, Ordering::Relaxed);
+            if let Some(rejected) = discarded.rejected {
+                rejected();
+            }
+        }
+    }
 
-        self.sender.as_ref().unwrap().send(job).unwrap();
+    // Agrega un worker si no se llegó al máximo
+    fn grow(&self) {
+        let shared = &self.shared;
+        let reserved = shared.stats.workers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| if workers < shared.max { Some(workers + 1) } else { None });
+        if reserved.is_err() {
+            return;
+        }
+        shared.queue.waiting.fetch_add(1, Ordering::SeqCst);
+        let id = shared.next_id.fetch_add(1, Ordering::Relaxed);
+        match Worker::spawn(id, Arc::clone(shared)) {
+            Ok(worker) => {
+                debug!("Spawned worker {} ({} running)", id, shared.stats.workers());
+                shared.workers.lock().unwrap_or_else(PoisonError::into_inner).push(worker);
+            }
+            Err(e) => {
+                error!("Could not spawn worker {}: {}", id, e);
+                shared.queue.waiting.fetch_sub(1, Ordering::SeqCst);
+                shared.stats.workers.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
     }
 }
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
+        // Los workers terminan los jobs que quedan en la cola y después salen
         drop(self.sender.take());
-        
-        for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
 
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+        // Se toma un thread a la vez sin mantener el lock, un worker que muere durante el apagado
+        // agrega su reemplazo a la lista y también se espera
+        loop {
+            let next = self.shared.workers.lock().unwrap_or_else(PoisonError::into_inner).iter_mut().find_map(|worker| worker.thread.take().map(|thread| (worker.id, thread)));
+            match next {
+                Some((id, thread)) => {
+                    debug!("Shutting down worker {}", id);
+                    let _ = thread.join();
+                }
+                None => break,
             }
         }
     }
@@ -61,27 +424,102 @@ struct Worker {
     thread: Option<thread::JoinHandle<()>>,
 }
 
-impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
+// Cuenta un worker ocupado mientras exista (también si el thread termina por un panic)
+struct Busy<'a> {
+    stats: &'a PoolStats,
+}
 
-            match message {
-                Ok(job) => {
-                    println!("Worker {id} got a job; executing.");
+impl Busy<'_> {
+    fn new(stats: &PoolStats) -> Busy<'_> {
+        stats.busy.fetch_add(1, Ordering::Relaxed);
+        Busy { stats }
+    }
+}
 
-                    job();
-                }
-                Err(_) => {
-                    println!("Worker {id} disconnected; shutting down.");
-                    break;
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        self.stats.busy.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Si el thread de un worker termina por un panic, al soltarse crea un worker nuevo con el mismo id
+struct Sentinel {
+    id: usize,
+    shared: Arc<Shared>,
+}
+
+impl Drop for Sentinel {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            error!("Worker {} died, spawning a replacement", self.id);
+            self.shared.queue.waiting.fetch_add(1, Ordering::SeqCst);
+            let mut workers = self.shared.workers.lock().unwrap_or_else(PoisonError::into_inner);
+            match Worker::spawn(self.id, Arc::clone(&self.shared)) {
+                Ok(worker) => match workers.iter_mut().find(|worker| worker.id == self.id) {
+                    Some(slot) => *slot = worker,
+                    None => workers.push(worker),
+                },
+                Err(e) => {
+                    error!("Could not respawn worker {}: {}", self.id, e);
+                    self.shared.queue.waiting.fetch_sub(1, Ordering::SeqCst);
+                    self.shared.stats.workers.fetch_sub(1, Ordering::SeqCst);
+                    workers.retain(|worker| worker.id != self.id);
                 }
             }
-        });
+        }
+    }
+}
 
-        Worker {
-            id, 
-            thread: Some(thread), 
+impl Worker {
+    // El worker ya tiene que estar contado en stats.workers y en waiting de la cola
+    fn spawn(id: usize, shared: Arc<Shared>) -> io::Result<Worker> {
+        let mut builder = thread::Builder::new().name(format!("{}-{}", shared.name, id));
+        if let Some(stack_size) = shared.stack_size {
+            builder = builder.stack_size(stack_size);
         }
+        let thread = builder.spawn(move || {
+            let _sentinel = Sentinel { id, shared: Arc::clone(&shared) };
+            let _span = info_span!("worker", id).entered();
+            let mut counted = true;
+            loop {
+                // Solo los workers que sobran del mínimo terminan por inactividad
+                let keep_alive = if shared.min < shared.max { Some(shared.keep_alive) } else { None };
+
+                match shared.queue.pop(&shared.stats, keep_alive, counted) {
+                    Pop::Task(task) => {
+                        shared.stats.queue_wait.observe(task.queued_at.elapsed());
+                        let _busy = Busy::new(&shared.stats);
+                        // Un panic en un job no mata al worker
+                        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(task.job)) {
+                            error!("Job panicked: {}", panic_message(&*payload));
+                        }
+                    }
+                    Pop::Idle => {
+                        let retired = shared.stats.workers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| if workers > shared.min { Some(workers - 1) } else { None });
+                        // Un job que se encoló mientras el worker dejaba de esperar puede no tener quién lo tome,
+                        // si hay alguno en la cola el worker vuelve a contarse y sigue
+                        if retired.is_ok() && !shared.queue.is_empty(&shared.stats) {
+                            let rejoined = shared.stats.workers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| if workers < shared.max { Some(workers + 1) } else { None });
+                            if rejoined.is_ok() {
+                                counted = false;
+                                continue;
+                            }
+                        }
+                        if retired.is_ok() {
+                            debug!("Worker {id} idle for {:?}; shutting down.", shared.keep_alive);
+                            shared.workers.lock().unwrap_or_else(PoisonError::into_inner).retain(|worker| worker.id != id);
+                            break;
+                        }
+                    }
+                    Pop::Closed => {
+                        debug!("Worker {id} disconnected; shutting down.");
+                        break;
+                    }
+                }
+                counted = false;
+            }
+        })?;
+
+        Ok(Worker { id, thread: Some(thread) })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,195 @@
+// Imports
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{atomic::{AtomicUsize, Ordering}, Mutex},
+    time::{Duration, Instant},
+};
+extern crate sha2;
+use self::sha2::{Digest, Sha256};
+
+use crate::http::auth::Identity;
+use crate::http::middleware::{Middleware, Next};
+use crate::http::parser::{create_response, Request, Response};
+
+// Cada cuántos requests se eliminan las llaves inactivas
+const PURGE_EVERY: usize = 1000;
+
+/// Algoritmo de limitación
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    // Ráfagas de hasta `capacity` requests, se recupera `per_second` requests por segundo
+    TokenBucket { capacity: u32, per_second: f64 },
+    // Como máximo `limit` requests en cualquier ventana de `window`
+    SlidingWindow { limit: u32, window: Duration },
+}
+
+/// De qué se saca la llave de cada cliente.
+/// Las conexiones por Unix socket no tienen IP: con Ip todas comparten la llave "ip:unknown"
+/// (un cliente puede agotar el límite de los demás), en ese caso conviene User o ApiKey.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyBy {
+    // IP del cliente (sin IP, por ejemplo por Unix socket, todos cuentan como uno solo)
+    Ip,
+    // Identidad autenticada (usuario o servicio); los anónimos por IP
+    User,
+    // Header X-API-Key validado con la función (como en ApiKeyAuth). Se guarda el hash de la llave, nunca la llave;
+    // los requests sin llave o con una que no es válida por IP
+    ApiKey(fn(&str) -> Option<Identity>),
+}
+
+// Estado de un cliente según el algoritmo
+enum Bucket {
+    Tokens { tokens: f64, updated: Instant },
+    Window(VecDeque<Instant>),
+}
+
+// Resultado de consumir un request
+struct Decision {
+    allowed: bool,
+    remaining: u32,
+    reset: Duration,
+    retry_after: Duration,
+}
+
+/// Middleware de rate limiting con las llaves en memoria.
+/// Agrega los headers RateLimit-Limit, RateLimit-Remaining y RateLimit-Reset a todos los responses
+/// y responde 429 con Retry-After cuando el cliente supera el límite.
+pub struct RateLimit {
+    algorithm: Algorithm,
+    key_by: KeyBy,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    requests: AtomicUsize,
+}
+
+impl RateLimit {
+    pub fn token_bucket(capacity: u32, per_second: f64) -> RateLimit {
+        RateLimit::new(Algorithm::TokenBucket { capacity: capacity.max(1), per_second })
+    }
+
+    pub fn sliding_window(limit: u32, window: Duration) -> RateLimit {
+        RateLimit::new(Algorithm::SlidingWindow { limit: limit.max(1), window })
+    }
+
+    fn new(algorithm: Algorithm) -> RateLimit {
+        RateLimit { algorithm, key_by: KeyBy::Ip, buckets: Mutex::new(HashMap::new()), requests: AtomicUsize::new(0) }
+    }
+
+    // Cambia cómo se identifica a los clientes (por defecto por IP)
+    pub fn key_by(mut self, key_by: KeyBy) -> RateLimit {
+        self.key_by = key_by;
+        self
+    }
+
+    fn limit(&self) -> u32 {
+        match self.algorithm {
+            Algorithm::TokenBucket { capacity, .. } => capacity,
+            Algorithm::SlidingWindow { limit, .. } => limit,
+        }
+    }
+
+    fn client_key(&self, request: &Request) -> String {
+        let ip = || match request.remote_addr {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        };
+        match self.key_by {
+            KeyBy::Ip => ip(),
+            KeyBy::User => request.identity.as_ref().map(|identity| format!("user:{}", identity.subject)).unwrap_or_else(ip),
+            KeyBy::ApiKey(lookup) => match request.header("X-API-Key").map(|key| key.trim()) {
+                Some(key) if lookup(key).is_some() => format!("key:{:x}", Sha256::digest(key.as_bytes())),
+                _ => ip(),
+            },
+        }
+    }
+
+    // Consume un request del cliente
+    fn acquire(&self, key: String) -> Decision {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        match self.algorithm {
+            Algorithm::TokenBucket { capacity, per_second } => {
+                let capacity = f64::from(capacity);
+                let bucket = buckets.entry(key).or_insert(Bucket::Tokens { tokens: capacity, updated: now });
+                let (tokens, updated) = match bucket {
+                    Bucket::Tokens { tokens, updated } => (tokens, updated),
+                    Bucket::Window(_) => unreachable!(),
+                };
+                *tokens = (*tokens + now.duration_since(*updated).as_secs_f64() * per_second).min(capacity);
+                *updated = now;
+
+                let allowed = *tokens >= 1.0;
+                if allowed {
+                    *tokens -= 1.0;
+                }
+                let seconds_until = |target: f64| if per_second > 0.0 { (target - *tokens).max(0.0) / per_second } else { f64::MAX };
+                Decision {
+                    allowed,
+                    remaining: *tokens as u32,
+                    reset: Duration::from_secs_f64(seconds_until(capacity).min(u32::MAX as f64)),
+                    retry_after: Duration::from_secs_f64(seconds_until(1.0).min(u32::MAX as f64)),
+                }
+            }
+            Algorithm::SlidingWindow { limit, window } => {
+                let bucket = buckets.entry(key).or_insert_with(|| Bucket::Window(VecDeque::new()));
+                let hits = match bucket {
+                    Bucket::Window(hits) => hits,
+                    Bucket::Tokens { .. } => unreachable!(),
+                };
+                while hits.front().map(|hit| now.duration_since(*hit) >= window).unwrap_or(false) {
+                    hits.pop_front();
+                }
+
+                let allowed = (hits.len() as u32) < limit;
+                if allowed {
+                    hits.push_back(now);
+                }
+                // El cupo se recupera cuando sale de la ventana el request más antiguo
+                let reset = hits.front().map(|oldest| window.saturating_sub(now.duration_since(*oldest))).unwrap_or_default();
+                Decision { allowed, remaining: limit - hits.len() as u32, reset, retry_after: reset }
+            }
+        }
+    }
+
+    // Elimina las llaves que ya volvieron a su estado inicial
+    fn purge_idle(&self) {
+        let now = Instant::now();
+        let algorithm = self.algorithm;
+        self.buckets.lock().unwrap().retain(|_, bucket| match (bucket, algorithm) {
+            (Bucket::Tokens { tokens, updated }, Algorithm::TokenBucket { capacity, per_second }) => {
+                *tokens + now.duration_since(*updated).as_secs_f64() * per_second < f64::from(capacity)
+            }
+            (Bucket::Window(hits), Algorithm::SlidingWindow { window, .. }) => {
+                hits.back().map(|last| now.duration_since(*last) < window).unwrap_or(false)
+            }
+            _ => false,
+        });
+    }
+}
+
+// Segundos redondeados hacia arriba, para no invitar a reintentar antes de tiempo
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, request: Request, next: Next) -> Response {
+        if self.requests.fetch_add(1, Ordering::Relaxed).is_multiple_of(PURGE_EVERY) {
+            self.purge_idle();
+        }
+
+        let decision = self.acquire(self.client_key(&request));
+        let mut response = if decision.allowed {
+            next.run(request)
+        } else {
+            let mut response = create_response(429, Some("[Error]: Too many requests".to_string()), None::<HashMap<String, String>>);
+            response.headers.insert("Retry-After".to_string(), ceil_secs(decision.retry_after).max(1).to_string());
+            response
+        };
+
+        response.headers.insert("RateLimit-Limit".to_string(), self.limit().to_string());
+        response.headers.insert("RateLimit-Remaining".to_string(), decision.remaining.to_string());
+        response.headers.insert("RateLimit-Reset".to_string(), ceil_secs(decision.reset).to_string());
+        response
+    }
+}
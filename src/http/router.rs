@@ -1,8 +1,16 @@
 use crate::http::parser::{Request, Response};
+use crate::http::sse::EventStream;
+use crate::http::websocket::WebSocket;
 
 // Tipo para las funciones controladoras de cada ruta
 pub type Controller = fn(Request) -> Response;
 
+// Tipo para los handlers de rutas WebSocket (reciben el request del handshake y el socket)
+pub type WebSocketHandler = fn(Request, WebSocket);
+
+// Tipo para los handlers de Server-Sent Events (reciben el request y el stream abierto)
+pub type EventStreamHandler = fn(Request, EventStream);
+
 // Lave para el hashmap que mapea [path, method] => controller
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub struct RouterKey{
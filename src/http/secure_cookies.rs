@@ -0,0 +1,182 @@
+// Imports
+use std::collections::HashSet;
+extern crate aes_gcm;
+extern crate base64;
+extern crate hmac;
+extern crate rand;
+extern crate sha2;
+extern crate tracing;
+use self::aes_gcm::{aead::{Aead, KeyInit, Payload}, Aes256Gcm, Nonce};
+use self::base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use self::hmac::{Hmac, Mac};
+use self::rand::Rng;
+use self::sha2::Sha256;
+use self::tracing::warn;
+
+use crate::http::parser::{Request, Response};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Llaves derivadas de un secreto del servidor
+struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl Key {
+    // Se derivan llaves distintas para firmar y cifrar a partir del mismo secreto
+    fn derive(secret: &[u8]) -> Key {
+        Key {
+            signing: hmac_sha256(secret, b"httprust cookie signing"),
+            encryption: hmac_sha256(secret, b"httprust cookie encryption"),
+        }
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new_from_slice(&self.encryption).expect("AES-256 key must be 32 bytes")
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Llaves para firmar y cifrar cookies.
+/// La primera llave se usa para firmar/cifrar, las demás solo para verificar (rotación de llaves).
+pub struct CookieKeys {
+    keys: Vec<Key>,
+}
+
+impl CookieKeys {
+    // Constructor con el secreto actual
+    pub fn new(secret: &[u8]) -> CookieKeys {
+        CookieKeys { keys: vec![Key::derive(secret)] }
+    }
+
+    // Agrega un secreto anterior que se sigue aceptando mientras las cookies viejas expiran
+    pub fn with_previous(mut self, secret: &[u8]) -> CookieKeys {
+        self.keys.push(Key::derive(secret));
+        self
+    }
+
+    // Firma un valor: "valor.firma", la firma cubre también el nombre de la cookie
+    pub fn sign(&self, name: &str, value: &str) -> String {
+        let mac = self.mac(&self.keys[0], name, value).finalize().into_bytes();
+        format!("{}.{}", value, URL_SAFE_NO_PAD.encode(mac))
+    }
+
+    // Verifica un valor firmado con cualquiera de las llaves y devuelve el valor original
+    pub fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        let (value, signature) = signed.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        self.keys
+            .iter()
+            .any(|key| self.mac(key, name, value).verify_slice(&signature).is_ok())
+            .then(|| value.to_string())
+    }
+
+    // Cifra un valor con AES-256-GCM: base64(nonce + texto cifrado), el nombre va como dato autenticado
+    pub fn encrypt(&self, name: &str, value: &str) -> String {
+        let nonce_bytes: [u8; 12] = rand::thread_rng().gen();
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let payload = Payload { msg: value.as_bytes(), aad: name.as_bytes() };
+        let ciphertext = self.keys[0].cipher().encrypt(nonce, payload).expect("AES-GCM encryption failed");
+
+        let mut sealed = nonce_bytes.to_vec();
+        sealed.extend(ciphertext);
+        URL_SAFE_NO_PAD.encode(sealed)
+    }
+
+    // Descifra un valor con cualquiera de las llaves, None si fue alterado
+    pub fn decrypt(&self, name: &str, sealed: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        if sealed.len() < 12 {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(12);
+
+        self.keys.iter().find_map(|key| {
+            let payload = Payload { msg: ciphertext, aad: name.as_bytes() };
+            key.cipher()
+                .decrypt(Nonce::from_slice(nonce), payload)
+                .ok()
+                .and_then(|plain| String::from_utf8(plain).ok())
+        })
+    }
+
+    fn mac(&self, key: &Key, name: &str, value: &str) -> HmacSha256 {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&key.signing).expect("HMAC accepts keys of any size");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+}
+
+/// Indica qué cookies se firman o cifran.
+/// Las cookies registradas que lleguen alteradas (o sin firma) se descartan antes de llegar a Request.cookies.
+pub struct CookiePolicy {
+    keys: CookieKeys,
+    signed: HashSet<String>,
+    encrypted: HashSet<String>,
+}
+
+impl CookiePolicy {
+    pub fn new(keys: CookieKeys) -> CookiePolicy {
+        CookiePolicy { keys, signed: HashSet::new(), encrypted: HashSet::new() }
+    }
+
+    // Registra una cookie que se envía firmada
+    pub fn signed(mut self, name: &str) -> CookiePolicy {
+        self.signed.insert(name.to_string());
+        self
+    }
+
+    // Registra una cookie que se envía cifrada (y autenticada)
+    pub fn encrypted(mut self, name: &str) -> CookiePolicy {
+        self.encrypted.insert(name.to_string());
+        self
+    }
+
+    // Verifica y descifra las cookies del request, descartando las inválidas
+    pub fn unseal_request(&self, request: &mut Request) {
+        let keys = &self.keys;
+        let signed = &self.signed;
+        let encrypted = &self.encrypted;
+
+        request.cookies = request
+            .cookies
+            .drain()
+            .filter_map(|(name, value)| {
+                let value = if signed.contains(&name) {
+                    keys.verify(&name, &value)
+                } else if encrypted.contains(&name) {
+                    keys.decrypt(&name, &value)
+                } else {
+                    Some(value)
+                };
+                if value.is_none() {
+                    warn!("Rejected tampered cookie '{}'", name);
+                }
+                value.map(|value| (name, value))
+            })
+            .collect();
+    }
+
+    // Firma y cifra las cookies del response (las cookies de borrado se dejan vacías)
+    pub fn seal_response(&self, response: &mut Response) {
+        for cookie in response.cookies.iter_mut() {
+            if cookie.value.is_empty() {
+                continue;
+            }
+            if self.signed.contains(&cookie.name) {
+                cookie.value = self.keys.sign(&cookie.name, &cookie.value);
+            } else if self.encrypted.contains(&cookie.name) {
+                cookie.value = self.keys.encrypt(&cookie.name, &cookie.value);
+            }
+        }
+    }
+}
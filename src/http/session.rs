@@ -0,0 +1,304 @@
+// Imports
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+extern crate rand;
+extern crate serde_json;
+extern crate tracing;
+use self::rand::Rng;
+use self::tracing::error;
+
+use crate::http::middleware::{Middleware, Next};
+use crate::http::parser::{Cookie, Request, Response, SameSite};
+
+// Datos que se guardan en una sesión
+pub type SessionData = HashMap<String, String>;
+
+// Cada cuánto se limpian las sesiones expiradas (en un thread aparte, no en el camino de los requests)
+const PURGE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Almacenamiento de sesiones del lado del servidor
+pub trait SessionStore: Send + Sync {
+    // Carga una sesión si existe y no ha expirado
+    fn load(&self, id: &str) -> Option<SessionData>;
+    // Guarda (o reemplaza) una sesión que expira después de ttl
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration);
+    // Elimina una sesión
+    fn destroy(&self, id: &str);
+    // Elimina todas las sesiones expiradas
+    fn purge_expired(&self);
+}
+
+/// Sesiones en memoria con expiración
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (SessionData, Instant)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        let sessions = self.sessions.lock().unwrap();
+        match sessions.get(id) {
+            Some((data, expires)) if *expires > Instant::now() => Some(data.clone()),
+            _ => None,
+        }
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) {
+        self.sessions.lock().unwrap().insert(id.to_string(), (data.clone(), Instant::now() + ttl));
+    }
+
+    fn destroy(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+
+    fn purge_expired(&self) {
+        let now = Instant::now();
+        self.sessions.lock().unwrap().retain(|_, (_, expires)| *expires > now);
+    }
+}
+
+/// Sesiones guardadas como archivos JSON en un directorio (sobreviven reinicios del servidor)
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    // Crea el directorio si no existe
+    pub fn new(dir: &str) -> std::io::Result<FileStore> {
+        fs::create_dir_all(dir)?;
+        Ok(FileStore { dir: PathBuf::from(dir) })
+    }
+
+    // Los ids son generados por el servidor; cualquier otro valor se rechaza para evitar path traversal
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        if id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Some(self.dir.join(format!("{}.json", id)))
+        } else {
+            None
+        }
+    }
+}
+
+// Segundos desde epoch, usado para la expiración en archivos
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        let path = self.path(id)?;
+        let contents = fs::read_to_string(&path).ok()?;
+        let stored: serde_json::Value = serde_json::from_str(&contents).ok()?;
+
+        if stored["expires"].as_u64().unwrap_or(0) <= unix_now() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        serde_json::from_value(stored["data"].clone()).ok()
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) {
+        let path = match self.path(id) {
+            Some(path) => path,
+            None => return,
+        };
+        let stored = serde_json::json!({ "expires": unix_now() + ttl.as_secs(), "data": data });
+        // Se escribe en un archivo temporal y se renombra: un crash o dos requests guardando la misma
+        // sesión nunca dejan un archivo a medias
+        let suffix: u64 = rand::thread_rng().gen();
+        let temp = self.dir.join(format!(".{}.{:016x}.tmp", id, suffix));
+        if let Err(e) = fs::write(&temp, stored.to_string()).and_then(|_| fs::rename(&temp, &path)) {
+            error!("Could not save session: {}", e);
+            let _ = fs::remove_file(&temp);
+        }
+    }
+
+    fn destroy(&self, id: &str) {
+        if let Some(path) = self.path(id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn purge_expired(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(id) = name.strip_suffix(".json") {
+                // load() borra el archivo si ya expiró
+                self.load(id);
+            } else if name.ends_with(".tmp") {
+                // Temporales que quedaron de un guardado interrumpido
+                let stale = entry.metadata().and_then(|m| m.modified()).ok().and_then(|m| m.elapsed().ok()).map(|age| age > Duration::from_secs(60)).unwrap_or(false);
+                if stale {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+}
+
+/// Sesión del request actual, disponible en los controllers con req.session()
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    inner: Arc<Mutex<SessionState>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    id: Option<String>,
+    data: SessionData,
+    changed: bool,
+    rotate: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    fn load(id: String, data: SessionData) -> Session {
+        let state = SessionState { id: Some(id), data, ..SessionState::default() };
+        Session { inner: Arc::new(Mutex::new(state)) }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.inner.lock().unwrap().data.get(key).cloned()
+    }
+
+    pub fn insert(&self, key: &str, value: &str) {
+        let mut state = self.inner.lock().unwrap();
+        state.data.insert(key.to_string(), value.to_string());
+        state.changed = true;
+    }
+
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut state = self.inner.lock().unwrap();
+        state.changed = true;
+        state.data.remove(key)
+    }
+
+    // Cambia el id de la sesión conservando sus datos (se debe llamar al hacer login para evitar session fixation)
+    pub fn rotate(&self) {
+        self.inner.lock().unwrap().rotate = true;
+    }
+
+    // Elimina la sesión del servidor y la cookie del cliente
+    pub fn destroy(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+// Genera un id de sesión aleatorio de 256 bits en hexadecimal
+fn generate_id() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Limpia el store cada PURGE_INTERVAL mientras exista
+fn purge_periodically(store: Weak<dyn SessionStore>) {
+    let spawned = thread::Builder::new().name("session-purge".to_string()).spawn(move || loop {
+        thread::sleep(PURGE_INTERVAL);
+        match store.upgrade() {
+            Some(store) => store.purge_expired(),
+            None => break,
+        }
+    });
+    if let Err(e) = spawned {
+        error!("Could not spawn session purge thread: {}", e);
+    }
+}
+
+/// Middleware que carga la sesión del request y guarda los cambios al terminar
+pub struct SessionMiddleware {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl SessionMiddleware {
+    // Las sesiones expiradas se limpian cada PURGE_INTERVAL en un thread que termina con el middleware
+    pub fn new(store: impl SessionStore + 'static) -> SessionMiddleware {
+        let store: Arc<dyn SessionStore> = Arc::new(store);
+        purge_periodically(Arc::downgrade(&store));
+        SessionMiddleware {
+            store,
+            cookie_name: "sid".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+        }
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> SessionMiddleware {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn cookie_name(mut self, name: &str) -> SessionMiddleware {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> SessionMiddleware {
+        self.secure = secure;
+        self
+    }
+
+    fn cookie(&self, id: &str) -> Cookie {
+        Cookie::new(&self.cookie_name, id)
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+            .max_age(self.ttl.as_secs() as i64)
+    }
+}
+
+impl Middleware for SessionMiddleware {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        let session = request
+            .cookies
+            .get(&self.cookie_name)
+            .and_then(|id| self.store.load(id).map(|data| Session::load(id.clone(), data)))
+            .unwrap_or_default();
+        request.session = session.clone();
+
+        let mut response = next.run(request);
+
+        let mut state = session.inner.lock().unwrap();
+        if state.destroyed {
+            if let Some(ref id) = state.id {
+                self.store.destroy(id);
+            }
+            response.remove_cookie(self.cookie(""));
+        } else if state.rotate || (state.changed && state.id.is_none()) {
+            // Sesión nueva o rotada: se genera un id nuevo y se borra el anterior
+            if let Some(ref old_id) = state.id {
+                self.store.destroy(old_id);
+            }
+            let id = generate_id();
+            self.store.save(&id, &state.data, self.ttl);
+            response.add_cookie(self.cookie(&id));
+            state.id = Some(id);
+        } else if let Some(ref id) = state.id {
+            // Se guarda en cada request para extender la expiración
+            self.store.save(id, &state.data, self.ttl);
+        }
+
+        response
+    }
+}
@@ -0,0 +1,134 @@
+// Imports
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::http::connection::Stream;
+use crate::http::parser::{create_response, format_head, Response};
+
+/// Evento de Server-Sent Events
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: u64,
+    pub event: String,
+    pub data: String,
+}
+
+impl Event {
+    // Formato text/event-stream: cada línea del data va en su propio campo "data:"
+    fn format(&self) -> String {
+        let mut formatted = format!("id: {}\nevent: {}\n", self.id, self.event);
+        for line in self.data.split('\n') {
+            formatted.push_str(&format!("data: {}\n", line));
+        }
+        formatted.push('\n');
+        formatted
+    }
+}
+
+/// Stream abierto hacia un cliente de SSE
+pub struct EventStream {
+    writer: Stream,
+}
+
+// Response que abre un stream. Pasa por los middlewares como cualquier otro, que le pueden
+// agregar headers (CORS, cookies) o cambiarlo por un error.
+pub fn response() -> Response {
+    let mut response = create_response(200, None, None::<HashMap<String, String>>);
+    response.headers.insert("Content-Type".to_string(), "text/event-stream".to_string());
+    response.headers.insert("Cache-Control".to_string(), "no-cache".to_string());
+    response.headers.insert("Connection".to_string(), "keep-alive".to_string());
+    response
+}
+
+impl EventStream {
+    // Envía los headers del response (sin Content-Length); a partir de aquí la conexión solo lleva eventos
+    pub fn open(mut writer: Stream, response: &Response) -> io::Result<EventStream> {
+        writer.write_all(format!("{}\r\n", format_head(response)).as_bytes())?;
+        writer.flush()?;
+        Ok(EventStream { writer })
+    }
+
+    // Envía un evento al cliente
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        self.writer.write_all(event.format().as_bytes())?;
+        self.writer.flush()
+    }
+
+    // Envía un comentario, sirve para mantener viva la conexión y detectar clientes desconectados
+    pub fn comment(&mut self, text: &str) -> io::Result<()> {
+        self.writer.write_all(format!(": {}\n\n", text).as_bytes())?;
+        self.writer.flush()
+    }
+}
+
+/// Buffer acotado de eventos para reenviarlos a clientes que se reconectan con Last-Event-ID
+pub struct EventBuffer {
+    capacity: usize,
+    state: Mutex<BufferState>,
+    changed: Condvar,
+}
+
+struct BufferState {
+    next_id: u64,
+    events: VecDeque<Event>,
+}
+
+impl EventBuffer {
+    pub fn new(capacity: usize) -> EventBuffer {
+        assert!(capacity > 0);
+
+        EventBuffer {
+            capacity,
+            state: Mutex::new(BufferState { next_id: 1, events: VecDeque::with_capacity(capacity) }),
+            changed: Condvar::new(),
+        }
+    }
+
+    // Agrega un evento (descartando el más viejo si el buffer está lleno) y despierta a los streams
+    pub fn push(&self, event: &str, data: String) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        if state.events.len() == self.capacity {
+            state.events.pop_front();
+        }
+        state.events.push_back(Event { id, event: event.to_string(), data });
+
+        self.changed.notify_all();
+        id
+    }
+
+    // Id del último evento generado (0 si no hay ninguno)
+    pub fn last_id(&self) -> u64 {
+        self.state.lock().unwrap().next_id - 1
+    }
+
+    // Espera hasta que haya eventos posteriores a last_id o pase el timeout.
+    // Devuelve None si last_id ya no está en el buffer y se perdieron eventos.
+    pub fn wait_after(&self, last_id: u64, timeout: Duration) -> Option<Vec<Event>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            let newest = state.next_id - 1;
+            let oldest = state.events.front().map(|e| e.id).unwrap_or(state.next_id);
+            if last_id > newest || last_id + 1 < oldest {
+                return None;
+            }
+            if last_id < newest {
+                return Some(state.events.iter().filter(|e| e.id > last_id).cloned().collect());
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Some(Vec::new());
+            }
+            state = self.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// Imports
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Read, Write},
+};
+extern crate base64;
+extern crate sha1;
+use self::base64::{engine::general_purpose::STANDARD, Engine};
+use self::sha1::{Digest, Sha1};
+
+use crate::http::connection::Stream;
+use crate::http::parser::{create_response, Request, Response};
+
+// GUID fijo definido por RFC 6455 para calcular Sec-WebSocket-Accept
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Tamaño máximo de un mensaje completo (sumando fragmentos)
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+// Opcodes de los frames
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+// Códigos de cierre usados por el servidor
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+pub const CLOSE_INVALID_PAYLOAD: u16 = 1007;
+pub const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Mensajes que se pueden enviar o recibir por un WebSocket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+/// Conexión WebSocket ya establecida (después del handshake)
+pub struct WebSocket {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    closed: bool,
+}
+
+// Un frame individual leído del stream
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+// Revisa si el request pide un upgrade a WebSocket
+pub fn is_upgrade_request(request: &Request) -> bool {
+    request
+        .header("Upgrade")
+        .map(|value| has_token(value, "websocket"))
+        .unwrap_or(false)
+}
+
+// Valida el handshake del cliente y construye la respuesta 101 (o el error correspondiente)
+pub fn handshake(request: &Request) -> Response {
+    if request.method != "GET" {
+        return create_response(405, Some("[Error]: WebSocket handshake must use GET".to_string()), None::<HashMap<String, String>>);
+    }
+    let connection_upgrade = request
+        .header("Connection")
+        .map(|value| has_token(value, "upgrade"))
+        .unwrap_or(false);
+    if !connection_upgrade {
+        return create_response(400, Some("[Error]: Missing 'Connection: Upgrade' header".to_string()), None::<HashMap<String, String>>);
+    }
+    if request.header("Sec-WebSocket-Version").map(|v| v.trim()) != Some("13") {
+        let mut response = create_response(426, Some("[Error]: Unsupported WebSocket version".to_string()), None::<HashMap<String, String>>);
+        response.headers.insert("Sec-WebSocket-Version".to_string(), "13".to_string());
+        return response;
+    }
+    let key = match request.header("Sec-WebSocket-Key") {
+        Some(key) if STANDARD.decode(key.trim()).map(|k| k.len() == 16).unwrap_or(false) => key.trim(),
+        _ => return create_response(400, Some("[Error]: Invalid Sec-WebSocket-Key".to_string()), None::<HashMap<String, String>>),
+    };
+
+    let mut response = create_response(101, None, None::<HashMap<String, String>>);
+    response.headers.insert("Upgrade".to_string(), "websocket".to_string());
+    response.headers.insert("Connection".to_string(), "Upgrade".to_string());
+    response.headers.insert("Sec-WebSocket-Accept".to_string(), accept_key(key));
+    response
+}
+
+// Calcula Sec-WebSocket-Accept = base64(sha1(key + GUID))
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+// Busca un token en un header con valores separados por coma (sin importar mayúsculas)
+fn has_token(value: &str, token: &str) -> bool {
+    value.split(',').any(|part| part.trim().eq_ignore_ascii_case(token))
+}
+
+impl WebSocket {
+    // Se recibe el reader con buffer para no perder bytes ya leídos después del handshake
+    pub fn new(reader: BufReader<Stream>, writer: Stream) -> WebSocket {
+        WebSocket { reader, writer, closed: false }
+    }
+
+    // Recibe el siguiente mensaje completo.
+    // Los pings se responden automáticamente con un pong y los fragmentos se unen en un solo mensaje.
+    pub fn recv(&mut self) -> io::Result<Message> {
+        if self.closed {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "WebSocket already closed"));
+        }
+
+        // Mensaje fragmentado en progreso: (opcode inicial, datos acumulados)
+        let mut fragmented: Option<(u8, Vec<u8>)> = None;
+
+        loop {
+            let frame = self.read_frame()?;
+
+            match frame.opcode {
+                // Los frames de control pueden llegar en medio de un mensaje fragmentado,
+                // en ese caso se atienden sin perder los fragmentos acumulados
+                OP_PING => {
+                    self.write_frame(OP_PONG, &frame.payload)?;
+                    if fragmented.is_none() {
+                        return Ok(Message::Ping(frame.payload));
+                    }
+                }
+                OP_PONG => {
+                    if fragmented.is_none() {
+                        return Ok(Message::Pong(frame.payload));
+                    }
+                }
+                OP_CLOSE => {
+                    let close = parse_close_payload(&frame.payload);
+                    let code = close.as_ref().map(|(code, _)| *code).unwrap_or(CLOSE_NORMAL);
+                    // Se responde el close y se termina la conexión
+                    self.write_frame(OP_CLOSE, &code.to_be_bytes())?;
+                    self.closed = true;
+                    return Ok(Message::Close(close));
+                }
+                OP_TEXT | OP_BINARY => {
+                    if fragmented.is_some() {
+                        return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Expected continuation frame"));
+                    }
+                    if frame.fin {
+                        return self.build_message(frame.opcode, frame.payload);
+                    }
+                    fragmented = Some((frame.opcode, frame.payload));
+                }
+                OP_CONTINUATION => {
+                    let (opcode, mut data) = match fragmented.take() {
+                        Some(partial) => partial,
+                        None => return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Unexpected continuation frame")),
+                    };
+                    if data.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(self.fail(CLOSE_TOO_BIG, "Message too big"));
+                    }
+                    data.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return self.build_message(opcode, data);
+                    }
+                    fragmented = Some((opcode, data));
+                }
+                _ => return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Unknown opcode")),
+            }
+        }
+    }
+
+    // Envía un mensaje (sin fragmentar)
+    pub fn send(&mut self, message: Message) -> io::Result<()> {
+        match message {
+            Message::Text(text) => self.write_frame(OP_TEXT, text.as_bytes()),
+            Message::Binary(data) => self.write_frame(OP_BINARY, &data),
+            Message::Ping(data) => self.write_frame(OP_PING, &data),
+            Message::Pong(data) => self.write_frame(OP_PONG, &data),
+            Message::Close(Some((code, reason))) => self.close(code, &reason),
+            Message::Close(None) => self.close(CLOSE_NORMAL, ""),
+        }
+    }
+
+    // Inicia el cierre de la conexión con un código y una razón
+    pub fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        // Los frames de control tienen un máximo de 125 bytes; la razón se corta sin partir un carácter UTF-8
+        let mut end = reason.len().min(123);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+        self.closed = true;
+        self.write_frame(OP_CLOSE, &payload)
+    }
+
+    // Convierte los datos completos en un mensaje, validando UTF-8 para texto
+    fn build_message(&mut self, opcode: u8, data: Vec<u8>) -> io::Result<Message> {
+        if opcode == OP_BINARY {
+            return Ok(Message::Binary(data));
+        }
+        match String::from_utf8(data) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => Err(self.fail(CLOSE_INVALID_PAYLOAD, "Invalid UTF-8 in text message")),
+        }
+    }
+
+    // Cierra la conexión por un error de protocolo y devuelve el error para el handler
+    fn fail(&mut self, code: u16, reason: &str) -> io::Error {
+        let _ = self.close(code, reason);
+        io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+    }
+
+    // Lee un frame del cliente y le quita la máscara
+    fn read_frame(&mut self) -> io::Result<Frame> {
+        let mut header = [0u8; 2];
+        self.reader.read_exact(&mut header)?;
+
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+
+        // No se negocian extensiones, entonces los bits reservados deben ir en 0
+        if header[0] & 0x70 != 0 {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Reserved bits set"));
+        }
+        // Todos los frames del cliente deben venir enmascarados
+        if !masked {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Client frames must be masked"));
+        }
+
+        let length = match header[1] & 0x7F {
+            126 => {
+                let mut ext = [0u8; 2];
+                self.reader.read_exact(&mut ext)?;
+                u16::from_be_bytes(ext) as u64
+            }
+            127 => {
+                let mut ext = [0u8; 8];
+                self.reader.read_exact(&mut ext)?;
+                u64::from_be_bytes(ext)
+            }
+            len => len as u64,
+        };
+
+        let is_control = opcode & 0x8 != 0;
+        if is_control && (!fin || length > 125) {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "Invalid control frame"));
+        }
+        if length > MAX_MESSAGE_SIZE as u64 {
+            return Err(self.fail(CLOSE_TOO_BIG, "Message too big"));
+        }
+
+        let mut mask = [0u8; 4];
+        self.reader.read_exact(&mut mask)?;
+
+        let mut payload = vec![0u8; length as usize];
+        self.reader.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        Ok(Frame { fin, opcode, payload })
+    }
+
+    // Escribe un frame del servidor (los frames del servidor no llevan máscara)
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len if len < 126 => frame.push(len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+
+        self.writer.write_all(&frame)?;
+        self.writer.flush()
+    }
+}
+
+// Extrae código y razón de un frame de close
+fn parse_close_payload(payload: &[u8]) -> Option<(u16, String)> {
+    if payload.len() < 2 {
+        return None;
+    }
+    let code = u16::from_be_bytes([payload[0], payload[1]]);
+    let reason = String::from_utf8_lossy(&payload[2..]).to_string();
+    Some((code, reason))
+}
@@ -0,0 +1,4 @@
+// El servidor http y la aplicación se exponen como librería para poder usarlos desde el binario
+pub mod http;
+pub mod app;
+pub mod config;
@@ -1,17 +1,136 @@
-mod http;
-mod app;
-    
+extern crate httprust;
+extern crate tracing;
+extern crate tracing_subscriber;
+use httprust::{app, config, http};
+use httprust::config::{Config, ConfigError};
+#[cfg(unix)]
+use httprust::http::connection::UnixSocket;
+use httprust::http::pool::{Overload, ThreadPool};
+use httprust::http::ratelimit::{KeyBy, RateLimit};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process;
+use std::time::Duration;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+// Configura los logs con el filtro de log.level (sintaxis de EnvFilter, ej: "info,httprust::http=debug")
+fn init_logging(level: &str) {
+    // Los logs van a stderr, stdout queda para el access log
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(level))
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+}
+
 fn main() {
-    let mut server = http::HttpServer::new(10);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match Config::load(&args) {
+        Ok(config) => config,
+        Err(ConfigError::Help) => {
+            print!("{}", config::usage());
+            return;
+        }
+        Err(e) => {
+            eprint!("{}", e);
+            process::exit(2);
+        }
+    };
+    init_logging(&config.log.level);
+    app::configure(&config.app);
+    if let Err(e) = app::create_admins() {
+        error!("{}", e);
+        process::exit(2);
+    }
+
+    let mut pool = ThreadPool::builder()
+        .min(config.server.pool_min_size.unwrap_or(config.server.pool_size))
+        .max(config.server.pool_size)
+        .keep_alive(Duration::from_secs(config.server.pool_keep_alive_secs));
+    if let Some(bytes) = config.server.worker_stack_bytes {
+        pool = pool.stack_size(bytes);
+    }
+    let mut server = http::HttpServer::with_pool(pool.build());
+    server.queue_limit(config.server.queue_capacity, Overload::parse(&config.server.overload_policy).unwrap_or(Overload::Reject));
+    server.max_body_size(config.server.max_body_bytes);
+    server.timeouts(Duration::from_secs(config.server.read_timeout_secs), Duration::from_secs(config.server.write_timeout_secs));
+    server.access_log(app::access_log(&config.log));
+    server.metrics("/metrics");
+    server.health("/healthz", "/readyz");
+    server.readiness_check("messages", app::messages_check);
+    server.readiness_check("pool", server.pool_check(20));
+    server.graceful_shutdown(Duration::from_secs(config.server.shutdown_drain_secs));
+    server.cookie_policy(app::cookie_policy());
+    server.middleware(app::cors()); // Primero, para contestar los preflight sin crear sesiones
+    server.middleware(app::session_middleware());
+    server.middleware(app::tokens::bearer_auth());
+    server.middleware(app::api_key_auth());
+    server.middleware(app::UserIdentity);
     
+    server.post("/register", app::register_controller);
     server.post("/login", app::login_controller);
+    server.post("/password", app::change_password_controller);
+    server.post("/token/refresh", app::refresh_token_controller);
+    server.post("/logout", app::logout_controller);
     server.get("/msg", app::get_messages_controller);
     server.get("/msg?", app::get_message_by_id_controller);
     server.post("/msg", app::post_message_controller);
     server.patch("/msg?", app::edit_existing_message_controller);
     server.put("/msg?", app::edit_or_create_message_controller);
     server.delete("/msg?", app::delete_message_by_id_controller);
+    server.events("/msg/events", app::message_events_controller);
+    server.websocket("/ws", app::messages_socket_controller);
+    server.get("/admin/users", app::list_users_controller);
+    server.patch("/admin/users?", app::change_role_controller);
+    server.get("/admin/api-keys", app::list_api_keys_controller);
+    server.post("/admin/api-keys", app::create_api_key_controller);
+    server.delete("/admin/api-keys?", app::revoke_api_key_controller);
+
+    // Límites por cliente: los intentos de login por IP y los mensajes nuevos por usuario o servicio
+    server.route_middleware("POST", "/login", RateLimit::sliding_window(10, Duration::from_secs(60)));
+    server.route_middleware("POST", "/register", RateLimit::sliding_window(5, Duration::from_secs(60 * 60)));
+    server.route_middleware("POST", "/msg", RateLimit::token_bucket(10, 0.5).key_by(KeyBy::User));
+
+    // Roles requeridos por ruta (la autorización por autor se hace en los controllers)
+    let writers = ["admin", "moderator", "member"];
+    server.require_roles("POST", "/msg", &writers);
+    server.require_roles("PUT", "/msg?", &writers);
+    server.require_roles("PATCH", "/msg?", &writers);
+    server.require_roles("DELETE", "/msg?", &writers);
+    // Las métricas muestran el tráfico por ruta y la saturación del pool: solo para admins
+    server.require_roles("GET", "/metrics", &["admin"]);
+    server.require_roles("GET", "/admin/users", &["admin"]);
+    server.require_roles("PATCH", "/admin/users?", &["admin"]);
+    server.require_roles("GET", "/admin/api-keys", &["admin"]);
+    server.require_roles("POST", "/admin/api-keys", &["admin"]);
+    server.require_roles("DELETE", "/admin/api-keys?", &["admin"]);
+
+    let listening = match config.server.unix_socket {
+        Some(ref path) => listen_unix(&server, path, config.server.unix_socket_mode()),
+        None => server.listen(&config.server.addresses()[..], |addrs| {
+            for addr in addrs {
+                info!("Listening on {}", addr);
+            }
+        }),
+    };
+    if let Err(e) = listening {
+        error!("Could not start the server: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(unix)]
+fn listen_unix(server: &http::HttpServer, path: &str, mode: Option<u32>) -> io::Result<()> {
+    let mut socket = UnixSocket::new(path);
+    if let Some(mode) = mode {
+        socket = socket.mode(mode);
+    }
+    server.listen_unix(socket, |path| info!("Listening on unix:{}", path.display()))
+}
 
-    let port: u16 = 8080;
-    server.listen(port, move || println!("Listening from port {}", port));
-}
\ No newline at end of file
+// La configuración no deja usar Unix sockets en otras plataformas
+#[cfg(not(unix))]
+fn listen_unix(_server: &http::HttpServer, _path: &str, _mode: Option<u32>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Unix sockets are not supported on this platform"))
+}
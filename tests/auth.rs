@@ -0,0 +1,160 @@
+// Autenticación HTTP Digest (nonces, nc, stale) y Basic
+extern crate base64;
+extern crate httprust;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use httprust::http::auth::{BasicAuth, CredentialVerifier, DigestAlgorithm, DigestAuth, StaticCredentials};
+use httprust::http::middleware::{Middleware, Next};
+use httprust::http::parser::{create_response, parse_request, Request, Response};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn credentials() -> Arc<dyn CredentialVerifier> {
+    Arc::new(StaticCredentials::new().user("alice", "s3cret", &["admin"]))
+}
+
+// Corre el middleware con GET /private y devuelve el response y el usuario que llegó al controller
+fn run(middleware: &dyn Middleware, authorization: &str) -> (Response, Option<String>) {
+    let raw = format!("GET /private HTTP/1.1\r\nHost: localhost\r\nAuthorization: {}\r\n\r\n", authorization);
+    let request = parse_request(&raw).unwrap();
+    let subject = RefCell::new(None);
+    let endpoint = |request: Request| {
+        *subject.borrow_mut() = request.identity.map(|identity| identity.subject);
+        create_response(200, None, None::<HashMap<String, String>>)
+    };
+    let response = middleware.handle(request, Next::new(&[], &endpoint));
+    (response, subject.into_inner())
+}
+
+// Parámetros del WWW-Authenticate de Digest (los valores del challenge no llevan comas)
+fn challenge(response: &Response) -> HashMap<String, String> {
+    let value = response.headers.get("WWW-Authenticate").expect("missing challenge");
+    let params = value.strip_prefix("Digest ").expect("not a Digest challenge");
+    params
+        .split(", ")
+        .filter_map(|param| param.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.trim_matches('"').to_string()))
+        .collect()
+}
+
+// Nonce nuevo del middleware, pedido como lo haría un cliente
+fn nonce(auth: &DigestAuth) -> String {
+    challenge(&run(auth, "Digest").0)["nonce"].clone()
+}
+
+// Authorization que calcularía un cliente con la contraseña indicada
+fn digest(algorithm: DigestAlgorithm, nonce: &str, nc: u32, password: &str) -> String {
+    let ha1 = algorithm.hash(&format!("alice:internal:{}", password));
+    let ha2 = algorithm.hash("GET:/private");
+    let nc = format!("{:08x}", nc);
+    let response = algorithm.hash(&format!("{}:{}:{}:0a4f113b:auth:{}", ha1, nonce, nc, ha2));
+    format!(
+        "Digest username=\"alice\", realm=\"internal\", nonce=\"{}\", uri=\"/private\", algorithm={}, qop=auth, nc={}, cnonce=\"0a4f113b\", response=\"{}\"",
+        nonce,
+        algorithm.as_str(),
+        nc,
+        response
+    )
+}
+
+#[test]
+fn digest_accepts_a_valid_response() {
+    for algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Md5] {
+        let auth = DigestAuth::new("internal", credentials()).algorithm(algorithm);
+        let (response, _) = run(&auth, "Digest");
+        assert_eq!(response.status_code, 401);
+        let params = challenge(&response);
+        assert_eq!(params["algorithm"], algorithm.as_str());
+
+        let (response, subject) = run(&auth, &digest(algorithm, &params["nonce"], 1, "s3cret"));
+        assert_eq!(response.status_code, 200);
+        assert_eq!(subject.as_deref(), Some("alice"));
+    }
+}
+
+#[test]
+fn digest_rejects_a_wrong_password() {
+    let auth = DigestAuth::new("internal", credentials());
+    let (response, subject) = run(&auth, &digest(DigestAlgorithm::Sha256, &nonce(&auth), 1, "guess"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(subject, None);
+}
+
+#[test]
+fn digest_rejects_a_replayed_nonce_count() {
+    let auth = DigestAuth::new("internal", credentials());
+    let nonce = nonce(&auth);
+
+    let first = digest(DigestAlgorithm::Sha256, &nonce, 1, "s3cret");
+    assert_eq!(run(&auth, &first).0.status_code, 200);
+    // La misma respuesta otra vez
+    let (replayed, subject) = run(&auth, &first);
+    assert_eq!(replayed.status_code, 401);
+    assert_eq!(subject, None);
+    assert_eq!(challenge(&replayed).get("stale").map(String::as_str), Some("true"));
+
+    assert_eq!(run(&auth, &digest(DigestAlgorithm::Sha256, &nonce, 3, "s3cret")).0.status_code, 200);
+    // Un nc menor que el último usado tampoco sirve
+    assert_eq!(run(&auth, &digest(DigestAlgorithm::Sha256, &nonce, 2, "s3cret")).0.status_code, 401);
+}
+
+#[test]
+fn digest_marks_expired_nonces_as_stale() {
+    // Sin tiempo de validez el nonce vence en cuanto cambia el segundo
+    let auth = DigestAuth::new("internal", credentials()).nonce_ttl(Duration::ZERO);
+    let nonce = nonce(&auth);
+    thread::sleep(Duration::from_millis(1100));
+
+    let (response, subject) = run(&auth, &digest(DigestAlgorithm::Sha256, &nonce, 1, "s3cret"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(subject, None);
+    let params = challenge(&response);
+    assert_eq!(params.get("stale").map(String::as_str), Some("true"));
+    assert_ne!(params["nonce"], nonce);
+}
+
+#[test]
+fn digest_rejects_forged_nonces() {
+    let auth = DigestAuth::new("internal", credentials());
+    // Nonce de otro servidor (otro secreto) y uno con la fecha cambiada
+    let foreign = nonce(&DigestAuth::new("internal", credentials()));
+    let mut altered = URL_SAFE_NO_PAD.decode(nonce(&auth)).unwrap();
+    altered[7] ^= 1;
+    let altered = URL_SAFE_NO_PAD.encode(altered);
+
+    for nonce in [foreign.as_str(), altered.as_str(), "bm90LWEtbm9uY2U"] {
+        let (response, subject) = run(&auth, &digest(DigestAlgorithm::Sha256, nonce, 1, "s3cret"));
+        assert_eq!(response.status_code, 401);
+        assert_eq!(subject, None);
+        assert_eq!(challenge(&response).get("stale"), None);
+    }
+}
+
+#[test]
+fn basic_accepts_valid_credentials() {
+    let auth = BasicAuth::new("internal", credentials());
+    let (response, subject) = run(&auth, &format!("Basic {}", STANDARD.encode("alice:s3cret")));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(subject.as_deref(), Some("alice"));
+}
+
+#[test]
+fn basic_rejects_malformed_credentials() {
+    let auth = BasicAuth::new("internal", credentials());
+    let cases = [
+        "Basic !!not-base64!!".to_string(),
+        format!("Basic {}", STANDARD.encode("alice-without-colon")),
+        format!("Basic {}", STANDARD.encode([0x61, 0x3a, 0xff, 0xfe])),
+        format!("Basic {}", STANDARD.encode("alice:wrong")),
+        format!("Bearer {}", STANDARD.encode("alice:s3cret")),
+        "Basic".to_string(),
+    ];
+    for authorization in cases.iter() {
+        let (response, subject) = run(&auth, authorization);
+        assert_eq!(response.status_code, 401, "{}", authorization);
+        assert_eq!(subject, None);
+        assert!(response.headers["WWW-Authenticate"].starts_with("Basic realm=\"internal\""));
+    }
+}
@@ -0,0 +1,117 @@
+// Carga de la configuración: prioridad entre archivo, entorno y CLI, formas de los flags y validación
+extern crate httprust;
+use httprust::config::{Config, ConfigError};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Las variables de entorno son del proceso: los tests que las tocan no pueden correr a la vez
+static ENV: Mutex<()> = Mutex::new(());
+
+// Archivo de configuración temporal con el contenido indicado
+struct ConfigFile(PathBuf);
+
+impl ConfigFile {
+    fn new(name: &str, contents: &str) -> ConfigFile {
+        let path = env::temp_dir().join(format!("httprust-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        ConfigFile(path)
+    }
+
+    fn arg(&self) -> String {
+        format!("--config={}", self.0.display())
+    }
+}
+
+impl Drop for ConfigFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+// Carga sin variables HTTPRUST_* del entorno salvo las indicadas
+fn load(vars: &[(&str, &str)], cli: &[String]) -> Result<Config, ConfigError> {
+    let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    for (name, _) in env::vars() {
+        if name.starts_with("HTTPRUST_") || name == "RUST_LOG" {
+            env::remove_var(name);
+        }
+    }
+    for (name, value) in vars {
+        env::set_var(name, value);
+    }
+    let config = Config::load(cli);
+    for (name, _) in vars {
+        env::remove_var(name);
+    }
+    config
+}
+
+fn invalid(result: Result<Config, ConfigError>) -> Vec<String> {
+    match result {
+        Err(ConfigError::Invalid(errors)) => errors,
+        Err(ConfigError::Help) => panic!("unexpected --help"),
+        Ok(_) => panic!("expected the configuration to be invalid"),
+    }
+}
+
+#[test]
+fn cli_overrides_environment_which_overrides_the_file() {
+    let file = ConfigFile::new("precedence", "[server]\nport = 1111\npool_size = 4\n");
+
+    let config = load(&[], &[file.arg()]).unwrap();
+    assert_eq!(config.server.port, 1111);
+
+    let config = load(&[("HTTPRUST_PORT", "2222")], &[file.arg()]).unwrap();
+    assert_eq!(config.server.port, 2222);
+    // Lo que no se cambia sigue saliendo del archivo
+    assert_eq!(config.server.pool_size, 4);
+
+    let config = load(&[("HTTPRUST_PORT", "2222")], &[file.arg(), "--port".to_string(), "3333".to_string()]).unwrap();
+    assert_eq!(config.server.port, 3333);
+}
+
+#[test]
+fn flags_accept_inline_and_separate_values() {
+    let config = load(&[], &args(&["--port=8081", "--pool-size", "3", "--cors-origins=https://a.example, https://b.example"])).unwrap();
+    assert_eq!(config.server.port, 8081);
+    assert_eq!(config.server.pool_size, 3);
+    assert_eq!(config.app.cors_origins, vec!["https://a.example", "https://b.example"]);
+
+    let errors = invalid(load(&[], &args(&["--unknown=1", "--port"])));
+    assert_eq!(errors, vec!["Unknown argument '--unknown=1'", "Missing value for --port"]);
+}
+
+#[test]
+fn invalid_values_are_reported_together() {
+    let errors = invalid(load(&[("HTTPRUST_POOL_MIN_SIZE", "8")], &args(&["--port", "0", "--pool-size=4", "--overload-policy", "drop-newest"])));
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    assert!(errors[0].starts_with("server.port: "));
+    assert!(errors[1].starts_with("server.pool_min_size: must not be greater than server.pool_size (4)"));
+    assert_eq!(errors[2], "server.overload_policy: must be block, reject or drop-oldest, got 'drop-newest'");
+
+    let errors = invalid(load(&[], &args(&["--port", "eighty"])));
+    assert_eq!(errors, vec!["--port: 'eighty' is not a valid number"]);
+}
+
+#[test]
+fn toml_errors_point_to_the_line_and_column() {
+    let file = ConfigFile::new("syntax", "[server]\nport = 8080\npool_size = = 4\n");
+    let errors = invalid(load(&[], &[file.arg()]));
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with(&format!("{}: ", file.0.display())));
+    assert!(errors[0].contains("line 3, column 13"), "{}", errors[0]);
+
+    // En el mensaje final cada línea del error queda indentada debajo de su guión
+    let message = ConfigError::Invalid(errors).to_string();
+    assert!(message.lines().skip(2).all(|line| line.starts_with("    ")), "{}", message);
+
+    let file = ConfigFile::new("unknown", "[server]\nprot = 8080\n");
+    let errors = invalid(load(&[], &[file.arg()]));
+    assert!(errors[0].contains("line 2, column 1") && errors[0].contains("unknown field `prot`"), "{}", errors[0]);
+}
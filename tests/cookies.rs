@@ -0,0 +1,35 @@
+// Serialización de Set-Cookie con los atributos de RFC 6265
+extern crate httprust;
+use httprust::http::parser::{Cookie, SameSite};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn serializes_every_attribute() {
+    let cookie = Cookie::new("__Secure-sid", "a b;c")
+        .domain(".example.com")
+        .path("/app")
+        .expires(UNIX_EPOCH + Duration::from_secs(784111777))
+        .max_age(3600)
+        .secure(true)
+        .http_only(true)
+        .same_site(SameSite::Lax);
+
+    assert_eq!(
+        cookie.to_header_value().unwrap(),
+        "__Secure-sid=a%20b%3Bc; Domain=example.com; Path=/app; Expires=Sun, 06 Nov 1994 08:49:37 GMT; Max-Age=3600; Secure; HttpOnly; SameSite=Lax"
+    );
+}
+
+#[test]
+fn serializes_only_the_attributes_that_are_set() {
+    assert_eq!(Cookie::new("theme", "dark").to_header_value().unwrap(), "theme=dark");
+    assert_eq!(Cookie::new("id", "1").same_site(SameSite::Strict).to_header_value().unwrap(), "id=1; SameSite=Strict");
+}
+
+#[test]
+fn rejects_invalid_attributes() {
+    assert!(Cookie::new("id", "1").domain("exa mple.com").to_header_value().is_err());
+    assert!(Cookie::new("id", "1").path("no-slash").to_header_value().is_err());
+    assert!(Cookie::new("id", "1").same_site(SameSite::None).to_header_value().is_err());
+    assert!(Cookie::new("__Host-id", "1").secure(true).path("/").domain("example.com").to_header_value().is_err());
+}
@@ -0,0 +1,110 @@
+// Middleware de CORS: orígenes permitidos, credenciales y rutas de event streams
+extern crate httprust;
+use httprust::http::cors::Cors;
+use httprust::http::middleware::{Middleware, Next};
+use httprust::http::parser::{create_response, parse_request, Request, Response};
+use httprust::http::sse::EventStream;
+use httprust::http::HttpServer;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+fn ok(_request: Request) -> Response {
+    create_response(200, Some("ok".to_string()), None::<HashMap<String, String>>)
+}
+
+fn run(cors: &Cors, method: &str, origin: &str, extra: &str) -> Response {
+    let raw = format!("{} /msg HTTP/1.1\r\nHost: localhost\r\nOrigin: {}\r\n{}\r\n", method, origin, extra);
+    cors.handle(parse_request(&raw).unwrap(), Next::new(&[], &ok))
+}
+
+#[test]
+fn listed_origins_are_echoed_with_credentials() {
+    let cors = Cors::new().allow_origin("https://app.example.com").allow_origin("https://*.example.org").allow_credentials(true);
+
+    let response = run(&cors, "GET", "https://app.example.com", "");
+    assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://app.example.com");
+    assert_eq!(response.headers["Access-Control-Allow-Credentials"], "true");
+    assert_eq!(response.headers["Vary"], "Origin");
+
+    let response = run(&cors, "GET", "https://eu.example.org", "");
+    assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://eu.example.org");
+
+    let response = run(&cors, "GET", "https://evil.example", "");
+    assert!(!response.headers.contains_key("Access-Control-Allow-Origin"));
+}
+
+#[test]
+fn rejected_origins_also_vary_by_origin() {
+    let cors = Cors::new().allow_origin("https://app.example.com");
+
+    let response = run(&cors, "GET", "https://evil.example", "");
+    assert!(!response.headers.contains_key("Access-Control-Allow-Origin"));
+    assert_eq!(response.headers["Vary"], "Origin");
+
+    let preflight = run(&cors, "OPTIONS", "https://evil.example", "Access-Control-Request-Method: POST\r\n");
+    assert_eq!(preflight.status_code, 403);
+    assert_eq!(preflight.headers["Vary"], "Origin");
+
+    let preflight = run(&cors, "OPTIONS", "https://app.example.com", "Access-Control-Request-Method: POST\r\n");
+    assert_eq!(preflight.status_code, 204);
+    assert!(preflight.headers["Vary"].split(", ").any(|value| value == "Origin"));
+}
+
+#[test]
+fn wildcard_origin_is_ignored_with_credentials() {
+    let cors = Cors::new().allow_origin("*").allow_credentials(true);
+
+    let response = run(&cors, "GET", "https://evil.example", "");
+    assert_eq!(response.status_code, 200);
+    assert!(!response.headers.contains_key("Access-Control-Allow-Origin"));
+    assert!(!response.headers.contains_key("Access-Control-Allow-Credentials"));
+
+    let preflight = run(&cors, "OPTIONS", "https://evil.example", "Access-Control-Request-Method: POST\r\n");
+    assert_eq!(preflight.status_code, 403);
+}
+
+#[test]
+fn wildcard_origin_without_credentials_answers_star() {
+    let cors = Cors::new().allow_origin("*");
+    let response = run(&cors, "GET", "https://anyone.example", "");
+    assert_eq!(response.headers["Access-Control-Allow-Origin"], "*");
+    assert!(!response.headers.contains_key("Access-Control-Allow-Credentials"));
+    // La respuesta es la misma para cualquier origen
+    assert!(!response.headers.contains_key("Vary"));
+}
+
+fn events(_request: Request, mut stream: EventStream) {
+    let _ = stream.comment("hello");
+}
+
+#[test]
+fn event_streams_get_cors_headers() {
+    let mut server = HttpServer::new(2);
+    server.middleware(Cors::new().allow_origin("https://front.example").allow_credentials(true));
+    server.events("/events", events);
+    let handle = server.start("127.0.0.1:0").unwrap();
+    assert!(handle.wait_ready(Duration::from_secs(5)));
+
+    let mut client = TcpStream::connect(handle.local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    client.write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\nOrigin: https://front.example\r\n\r\n").unwrap();
+
+    let mut head = Vec::new();
+    for line in BufReader::new(client).lines() {
+        let line = line.unwrap();
+        if line.is_empty() {
+            break;
+        }
+        head.push(line);
+    }
+    assert_eq!(head[0], "HTTP/1.1 200 OK");
+    assert!(head.contains(&"Content-Type: text/event-stream".to_string()));
+    assert!(head.contains(&"Access-Control-Allow-Origin: https://front.example".to_string()));
+    assert!(head.contains(&"Access-Control-Allow-Credentials: true".to_string()));
+    assert!(!head.iter().any(|line| line.starts_with("Content-Length")));
+
+    handle.shutdown();
+    handle.join().unwrap();
+}
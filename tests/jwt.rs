@@ -0,0 +1,105 @@
+// Emisión y validación de JWT con HS256 y RS256
+extern crate base64;
+extern crate httprust;
+extern crate rsa;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use httprust::http::jwt::{unix_now, Claims, Jwt, JwtError};
+use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::rand_core::OsRng;
+use rsa::RsaPrivateKey;
+
+const SECRET: &[u8] = b"jwt-test-secret-with-at-least-32-bytes";
+
+fn claims(exp: u64) -> Claims {
+    Claims { sub: "alice".to_string(), iat: unix_now(), exp, roles: vec!["member".to_string()] }
+}
+
+// Llaves RSA en PEM: (privada PKCS#8, pública SPKI)
+fn rsa_keys() -> (String, String) {
+    let private = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+    let private_pem = private.to_pkcs8_pem(LineEnding::LF).unwrap().to_string();
+    let public_pem = private.to_public_key().to_public_key_pem(LineEnding::LF).unwrap();
+    (private_pem, public_pem)
+}
+
+// Token con un header y una firma arbitrarios
+fn forge(header: &str, token: &str, signature: &[u8]) -> String {
+    let payload = token.split('.').nth(1).unwrap();
+    format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(header), payload, URL_SAFE_NO_PAD.encode(signature))
+}
+
+#[test]
+fn hs256_round_trip() {
+    let jwt = Jwt::hs256(SECRET);
+    let token = jwt.encode(&claims(unix_now() + 60)).unwrap();
+    let decoded = jwt.decode(&token).unwrap();
+    assert_eq!(decoded.sub, "alice");
+    assert_eq!(decoded.roles, vec!["member".to_string()]);
+}
+
+#[test]
+fn rs256_round_trip_and_public_key_only_verifies() {
+    let (private_pem, public_pem) = rsa_keys();
+    let signer = Jwt::rs256_private_pem(&private_pem).unwrap();
+    let verifier = Jwt::rs256_public_pem(&public_pem).unwrap();
+
+    let token = signer.encode(&claims(unix_now() + 60)).unwrap();
+    assert_eq!(signer.decode(&token).unwrap().sub, "alice");
+    assert_eq!(verifier.decode(&token).unwrap().sub, "alice");
+    assert!(verifier.encode(&claims(unix_now() + 60)).is_err());
+}
+
+#[test]
+fn rejects_tampered_tokens() {
+    let jwt = Jwt::hs256(SECRET);
+    let token = jwt.encode(&claims(unix_now() + 60)).unwrap();
+    let mut parts: Vec<String> = token.split('.').map(String::from).collect();
+
+    // Payload cambiado con la firma original
+    let admin = serde_json::json!({ "sub": "alice", "iat": 0, "exp": unix_now() + 60, "roles": ["admin"] });
+    let tampered = format!("{}.{}.{}", parts[0], URL_SAFE_NO_PAD.encode(admin.to_string()), parts[2]);
+    assert_eq!(jwt.decode(&tampered).unwrap_err(), JwtError::InvalidSignature);
+
+    // Un bit de la firma cambiado
+    let mut signature = URL_SAFE_NO_PAD.decode(&parts[2]).unwrap();
+    signature[0] ^= 1;
+    parts[2] = URL_SAFE_NO_PAD.encode(signature);
+    assert_eq!(jwt.decode(&parts.join(".")).unwrap_err(), JwtError::InvalidSignature);
+
+    // Firmado con otro secreto
+    let other = Jwt::hs256(b"another-secret-with-at-least-32-bytes").encode(&claims(unix_now() + 60)).unwrap();
+    assert_eq!(jwt.decode(&other).unwrap_err(), JwtError::InvalidSignature);
+
+    assert_eq!(jwt.decode("not.a.jwt.at-all").unwrap_err(), JwtError::Malformed);
+    assert_eq!(jwt.decode("missing-parts").unwrap_err(), JwtError::Malformed);
+}
+
+#[test]
+fn rejects_none_and_swapped_algorithms() {
+    let hs256 = Jwt::hs256(SECRET);
+    let token = hs256.encode(&claims(unix_now() + 60)).unwrap();
+
+    // "none" sin firma
+    let none = forge(r#"{"alg":"none","typ":"JWT"}"#, &token, b"");
+    assert_eq!(hs256.decode(&none).unwrap_err(), JwtError::UnsupportedAlgorithm);
+
+    // Un token HS256 firmado con la llave pública como secreto no pasa por un verificador RS256
+    let (private_pem, public_pem) = rsa_keys();
+    let rs256 = Jwt::rs256_private_pem(&private_pem).unwrap();
+    let confused = Jwt::hs256(public_pem.as_bytes()).encode(&claims(unix_now() + 60)).unwrap();
+    assert_eq!(rs256.decode(&confused).unwrap_err(), JwtError::UnsupportedAlgorithm);
+
+    // Ni un RS256 por uno HS256
+    let rs256_token = rs256.encode(&claims(unix_now() + 60)).unwrap();
+    assert_eq!(hs256.decode(&rs256_token).unwrap_err(), JwtError::UnsupportedAlgorithm);
+}
+
+#[test]
+fn expiration_allows_30_seconds_of_clock_skew() {
+    let jwt = Jwt::hs256(SECRET);
+    let skewed = jwt.encode(&claims(unix_now() - 10)).unwrap();
+    assert!(jwt.decode(&skewed).is_ok());
+
+    let expired = jwt.encode(&claims(unix_now() - 31)).unwrap();
+    assert_eq!(jwt.decode(&expired).unwrap_err(), JwtError::Expired);
+}
@@ -0,0 +1,158 @@
+// Pool: cola llena, workers que terminan por inactividad y jobs que hacen panic
+extern crate httprust;
+use httprust::http::parser::{create_response, Request, Response};
+use httprust::http::pool::{Overload, ThreadPool};
+use httprust::http::HttpServer;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn jobs_submitted_while_the_last_worker_retires_still_run() {
+    let keep_alive = Duration::from_millis(1);
+    let submitters: Vec<_> = (0..4u64)
+        .map(|n| {
+            thread::spawn(move || {
+                // Sin mínimo, cuando el worker termina por inactividad el pool queda vacío
+                let pool = ThreadPool::builder().min(0).max(1).keep_alive(keep_alive).build();
+                let (sender, receiver) = mpsc::channel();
+                for i in 0..1000u64 {
+                    let sender = sender.clone();
+                    pool.execute(move || sender.send(i).unwrap());
+                    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(i), "job {} was never run", i);
+                    // Entre 0.5 y 1.5 veces el keep_alive, para encolar cuando el worker deja de esperar
+                    thread::sleep(keep_alive / 2 + Duration::from_micros((i * 37 + n * 11) % 1000));
+                }
+            })
+        })
+        .collect();
+    for submitter in submitters {
+        submitter.join().unwrap();
+    }
+}
+
+// Pool de un worker ocupado hasta que se manda algo por el sender que devuelve, con la cola limitada a un job
+fn busy_pool(overload: Overload) -> (ThreadPool, mpsc::Sender<()>) {
+    let pool = ThreadPool::new(1);
+    pool.set_queue_limit(1, overload);
+    let (release, gate) = mpsc::channel();
+    let (started, running) = mpsc::channel();
+    pool.execute(move || {
+        started.send(()).unwrap();
+        gate.recv().unwrap();
+    });
+    running.recv_timeout(Duration::from_secs(5)).unwrap();
+    (pool, release)
+}
+
+#[test]
+fn a_full_queue_applies_the_overload_policy() {
+    for overload in [Overload::Reject, Overload::DropOldest] {
+        let (pool, release) = busy_pool(overload);
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..3 {
+            let (ran, rejected) = (sender.clone(), sender.clone());
+            pool.execute_or(move || ran.send(Ok(i)).unwrap(), move || rejected.send(Err(i)).unwrap());
+        }
+        release.send(()).unwrap();
+        drop(pool);
+        let mut outcomes: Vec<_> = receiver.try_iter().collect();
+        outcomes.sort();
+        match overload {
+            Overload::Reject => assert_eq!(outcomes, [Ok(0), Err(1), Err(2)]),
+            _ => assert_eq!(outcomes, [Ok(2), Err(0), Err(1)]),
+        }
+    }
+
+    // Con Block el que encola espera hasta que un worker saca un job de la cola
+    let (pool, release) = busy_pool(Overload::Block);
+    let stats = pool.stats();
+    let (sender, receiver) = mpsc::channel();
+    let producer = thread::spawn(move || {
+        for i in 0..100 {
+            let sender = sender.clone();
+            pool.execute(move || sender.send(i).unwrap());
+        }
+    });
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(stats.queue_depth(), 1);
+    assert!(receiver.try_recv().is_err());
+    release.send(()).unwrap();
+    producer.join().unwrap();
+    let ran: Vec<_> = (0..100).map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap()).collect();
+    assert_eq!(ran, (0..100).collect::<Vec<_>>());
+    assert_eq!(stats.rejected(), 0);
+}
+
+#[test]
+fn a_job_that_panics_holding_a_lock_does_not_stop_the_pool() {
+    let pool = ThreadPool::new(2);
+    let counter = Arc::new(Mutex::new(0));
+
+    let held = Arc::clone(&counter);
+    pool.execute(move || {
+        let _guard = held.lock().unwrap();
+        panic!("job failed with the lock held");
+    });
+
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..10 {
+        let (counter, sender) = (Arc::clone(&counter), sender.clone());
+        pool.execute(move || {
+            let mut count = counter.lock().unwrap_or_else(PoisonError::into_inner);
+            *count += 1;
+            sender.send(*count).unwrap();
+        });
+    }
+    for _ in 0..10 {
+        receiver.recv_timeout(Duration::from_secs(5)).expect("the pool stopped running jobs");
+    }
+    assert!(counter.is_poisoned());
+    assert_eq!(*counter.lock().unwrap_or_else(PoisonError::into_inner), 10);
+    assert_eq!(pool.size(), 2);
+}
+
+static VISITS: Mutex<u32> = Mutex::new(0);
+
+fn panics_with_the_lock(_request: Request) -> Response {
+    let _visits = VISITS.lock().unwrap_or_else(PoisonError::into_inner);
+    panic!("controller failed with the lock held");
+}
+
+fn visits(_request: Request) -> Response {
+    let mut visits = VISITS.lock().unwrap_or_else(PoisonError::into_inner);
+    *visits += 1;
+    create_response(200, Some(visits.to_string()), None::<HashMap<String, String>>)
+}
+
+fn get(addr: SocketAddr, path: &str) -> String {
+    let mut client = TcpStream::connect(addr).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    client.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).as_bytes()).unwrap();
+    let mut raw = String::new();
+    client.read_to_string(&mut raw).unwrap();
+    raw
+}
+
+#[test]
+fn endpoints_keep_serving_after_a_controller_panics_holding_a_lock() {
+    let mut server = HttpServer::new(1);
+    server.get("/panic", panics_with_the_lock);
+    server.get("/visits", visits);
+    let handle = server.start("127.0.0.1:0").unwrap();
+    assert!(handle.wait_ready(Duration::from_secs(5)));
+    let addr = handle.local_addr().unwrap();
+
+    assert!(get(addr, "/panic").starts_with("HTTP/1.1 500 "));
+    for expected in 1..=3 {
+        let raw = get(addr, "/visits");
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"), "{}", raw);
+        assert!(raw.ends_with(&format!("\r\n\r\n{}", expected)));
+    }
+
+    handle.shutdown();
+    handle.join().unwrap();
+}
@@ -0,0 +1,41 @@
+// Rate limiting por API key: solo las llaves válidas tienen su propio cupo
+extern crate httprust;
+use httprust::http::auth::Identity;
+use httprust::http::middleware::{Middleware, Next};
+use httprust::http::parser::{create_response, parse_request, Request, Response};
+use httprust::http::ratelimit::{KeyBy, RateLimit};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn ok(_request: Request) -> Response {
+    create_response(200, None, None::<HashMap<String, String>>)
+}
+
+fn lookup(key: &str) -> Option<Identity> {
+    match key {
+        "hrk_good" | "hrk_other" => Some(Identity { subject: format!("service:{}", key), roles: Vec::new() }),
+        _ => None,
+    }
+}
+
+fn status(limit: &RateLimit, api_key: &str) -> u16 {
+    let raw = format!("GET /msg HTTP/1.1\r\nHost: localhost\r\nX-API-Key: {}\r\n\r\n", api_key);
+    limit.handle(parse_request(&raw).unwrap(), Next::new(&[], &ok)).status_code
+}
+
+#[test]
+fn valid_api_keys_get_their_own_bucket() {
+    let limit = RateLimit::sliding_window(1, Duration::from_secs(60)).key_by(KeyBy::ApiKey(lookup));
+    assert_eq!(status(&limit, "hrk_good"), 200);
+    assert_eq!(status(&limit, "hrk_good"), 429);
+    assert_eq!(status(&limit, "hrk_other"), 200);
+}
+
+#[test]
+fn invalid_api_keys_share_the_ip_bucket() {
+    let limit = RateLimit::sliding_window(1, Duration::from_secs(60)).key_by(KeyBy::ApiKey(lookup));
+    // Cambiar la llave en cada request no da un cupo nuevo
+    assert_eq!(status(&limit, "made-up-1"), 200);
+    assert_eq!(status(&limit, "made-up-2"), 429);
+    assert_eq!(status(&limit, "hrk_good"), 200);
+}
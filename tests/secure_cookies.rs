@@ -0,0 +1,102 @@
+// Cookies firmadas (HMAC-SHA256) y cifradas (AES-256-GCM) con rotación de llaves
+extern crate base64;
+extern crate httprust;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use httprust::http::parser::parse_request;
+use httprust::http::secure_cookies::{CookieKeys, CookiePolicy};
+
+const CURRENT: &[u8] = b"current-secret-with-at-least-32-bytes";
+const PREVIOUS: &[u8] = b"previous-secret-with-at-least-32-bytes";
+
+#[test]
+fn signed_values_round_trip() {
+    let keys = CookieKeys::new(CURRENT);
+    let signed = keys.sign("sid", "abc123");
+    assert!(signed.starts_with("abc123."));
+    assert_eq!(keys.verify("sid", &signed), Some("abc123".to_string()));
+}
+
+#[test]
+fn previous_keys_still_verify_after_rotation() {
+    let old = CookieKeys::new(PREVIOUS);
+    let signed = old.sign("sid", "abc123");
+    let encrypted = old.encrypt("prefs", "dark");
+
+    let rotated = CookieKeys::new(CURRENT).with_previous(PREVIOUS);
+    assert_eq!(rotated.verify("sid", &signed), Some("abc123".to_string()));
+    assert_eq!(rotated.decrypt("prefs", &encrypted), Some("dark".to_string()));
+    // Sin el secreto anterior las cookies viejas dejan de valer
+    assert_eq!(CookieKeys::new(CURRENT).verify("sid", &signed), None);
+    // Las cookies nuevas se firman con el secreto actual
+    assert_eq!(CookieKeys::new(CURRENT).verify("sid", &rotated.sign("sid", "abc123")), Some("abc123".to_string()));
+}
+
+#[test]
+fn tampered_signatures_are_rejected() {
+    let keys = CookieKeys::new(CURRENT);
+    let signed = keys.sign("sid", "abc123");
+    let (value, signature) = signed.rsplit_once('.').unwrap();
+
+    // Otro valor con la misma firma
+    assert_eq!(keys.verify("sid", &format!("abc124.{}", signature)), None);
+    // La misma firma con un bit cambiado
+    let mut mac = URL_SAFE_NO_PAD.decode(signature).unwrap();
+    mac[0] ^= 1;
+    assert_eq!(keys.verify("sid", &format!("{}.{}", value, URL_SAFE_NO_PAD.encode(mac))), None);
+    // La firma cubre el nombre de la cookie
+    assert_eq!(keys.verify("other", &signed), None);
+    // Sin firma
+    assert_eq!(keys.verify("sid", "abc123"), None);
+}
+
+#[test]
+fn encrypted_values_round_trip() {
+    let keys = CookieKeys::new(CURRENT);
+    let encrypted = keys.encrypt("prefs", "theme=dark; lang=es");
+    assert!(!encrypted.contains("dark"));
+    assert_eq!(keys.decrypt("prefs", &encrypted), Some("theme=dark; lang=es".to_string()));
+    // Cada cifrado usa un nonce nuevo
+    assert_ne!(keys.encrypt("prefs", "theme=dark; lang=es"), encrypted);
+}
+
+#[test]
+fn encrypted_values_are_bound_to_the_cookie_name() {
+    let keys = CookieKeys::new(CURRENT);
+    let encrypted = keys.encrypt("prefs", "admin=false");
+    assert_eq!(keys.decrypt("session", &encrypted), None);
+}
+
+#[test]
+fn truncated_or_altered_ciphertexts_are_rejected() {
+    let keys = CookieKeys::new(CURRENT);
+    let sealed = URL_SAFE_NO_PAD.decode(keys.encrypt("prefs", "dark")).unwrap();
+
+    // Sin el tag de autenticación
+    assert_eq!(keys.decrypt("prefs", &URL_SAFE_NO_PAD.encode(&sealed[..sealed.len() - 1])), None);
+    // Más corto que el nonce
+    assert_eq!(keys.decrypt("prefs", &URL_SAFE_NO_PAD.encode(&sealed[..8])), None);
+    // Un bit del texto cifrado cambiado
+    let mut altered = sealed.clone();
+    altered[12] ^= 1;
+    assert_eq!(keys.decrypt("prefs", &URL_SAFE_NO_PAD.encode(altered)), None);
+    // No es base64
+    assert_eq!(keys.decrypt("prefs", "not base64!"), None);
+}
+
+#[test]
+fn policy_drops_tampered_cookies_from_requests() {
+    let keys = CookieKeys::new(CURRENT);
+    let signed = keys.sign("sid", "abc123");
+    let encrypted = keys.encrypt("prefs", "dark");
+    let moved = keys.encrypt("other", "dark");
+    let policy = CookiePolicy::new(keys).signed("sid").encrypted("prefs").encrypted("moved");
+
+    let raw = format!("GET / HTTP/1.1\r\nHost: localhost\r\nCookie: sid={}; prefs={}; moved={}; plain=1\r\n\r\n", signed, encrypted, moved);
+    let mut request = parse_request(&raw).unwrap();
+    policy.unseal_request(&mut request);
+
+    assert_eq!(request.cookies.get("sid").map(String::as_str), Some("abc123"));
+    assert_eq!(request.cookies.get("prefs").map(String::as_str), Some("dark"));
+    assert_eq!(request.cookies.get("moved"), None);
+    assert_eq!(request.cookies.get("plain").map(String::as_str), Some("1"));
+}
@@ -0,0 +1,129 @@
+// Servidor en el mismo proceso: start, wait_ready, shutdown y join
+extern crate httprust;
+use httprust::http::auth::Identity;
+use httprust::http::middleware::{Middleware, Next};
+use httprust::http::parser::{create_response, Request, Response};
+use httprust::http::HttpServer;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// GET con Connection: close, devuelve el response completo
+fn get(addr: SocketAddr, path: &str, headers: &str) -> String {
+    let mut client = TcpStream::connect(addr).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    client.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n", path, headers).as_bytes()).unwrap();
+    let mut raw = String::new();
+    client.read_to_string(&mut raw).unwrap();
+    raw
+}
+
+fn hello(_request: Request) -> Response {
+    create_response(200, Some("hello".to_string()), None::<HashMap<String, String>>)
+}
+
+#[test]
+fn serves_requests_until_shutdown() {
+    let mut server = HttpServer::new(2);
+    server.get("/hello", hello);
+    let handle = server.start("127.0.0.1:0").unwrap();
+    assert!(handle.wait_ready(Duration::from_secs(5)));
+    let addr = handle.local_addr().unwrap();
+    assert_ne!(addr.port(), 0);
+
+    let raw = get(addr, "/hello", "");
+    assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(raw.ends_with("\r\n\r\nhello"));
+
+    handle.shutdown();
+    handle.join().unwrap();
+    assert!(TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn shutdown_does_not_depend_on_connecting_to_the_listener() {
+    // Escuchando en todas las interfaces, el apagado no se conecta a ningún listener para despertarlo
+    let handle = HttpServer::new(1).start("0.0.0.0:0").unwrap();
+    assert!(handle.wait_ready(Duration::from_secs(5)));
+
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        handle.shutdown();
+        done.send(handle.join().is_ok()).unwrap();
+    });
+    assert_eq!(finished.recv_timeout(Duration::from_secs(5)), Ok(true));
+}
+
+// Autentica con "X-Test-User: usuario:rol"
+struct TestUser;
+
+impl Middleware for TestUser {
+    fn handle(&self, mut request: Request, next: Next) -> Response {
+        if let Some((subject, role)) = request.header("X-Test-User").and_then(|user| user.split_once(':')) {
+            request.identity = Some(Identity { subject: subject.to_string(), roles: vec![role.to_string()] });
+        }
+        next.run(request)
+    }
+}
+
+#[test]
+fn metrics_respect_route_middlewares() {
+    let mut server = HttpServer::new(2);
+    server.middleware(TestUser);
+    server.metrics("/metrics");
+    server.require_roles("GET", "/metrics", &["admin"]);
+    let handle = server.start("127.0.0.1:0").unwrap();
+    assert!(handle.wait_ready(Duration::from_secs(5)));
+    let addr = handle.local_addr().unwrap();
+
+    assert!(get(addr, "/metrics", "").starts_with("HTTP/1.1 401 "));
+    assert!(get(addr, "/metrics", "X-Test-User: bob:member\r\n").starts_with("HTTP/1.1 403 "));
+    let raw = get(addr, "/metrics", "X-Test-User: alice:admin\r\n");
+    assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(raw.contains("httprust_http_requests_total"));
+
+    handle.shutdown();
+    handle.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn private_unix_sockets_can_share_a_directory() {
+    use httprust::http::connection::UnixSocket;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+
+    let dir = std::env::temp_dir().join(format!("httprust-sockets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let handles: Vec<_> = ["a.sock", "b.sock"]
+        .iter()
+        .map(|name| {
+            let mut server = HttpServer::new(1);
+            server.get("/hello", hello);
+            server.start_unix(UnixSocket::new(dir.join(name)).mode(0o660)).unwrap()
+        })
+        .collect();
+
+    for name in ["a.sock", "b.sock"] {
+        let path = dir.join(name);
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"GET /hello HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+    // Los directorios temporales del bind no quedan
+    let mut entries: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    entries.sort();
+    assert_eq!(entries, ["a.sock", "b.sock"]);
+
+    for handle in handles {
+        handle.shutdown();
+        handle.join().unwrap();
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,45 @@
+// Refresh tokens de la app: rotación, revocación y cambio de contraseña
+extern crate httprust;
+use httprust::app::{self, tokens, users};
+use httprust::http::auth::Identity;
+use httprust::http::parser::parse_request;
+
+fn refresh_token(issued: &serde_json::Value) -> String {
+    issued["refresh_token"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn refresh_tokens_are_single_use() {
+    let first = refresh_token(&tokens::issue("rotating").unwrap());
+    let second = tokens::refresh(&first).unwrap().unwrap();
+    assert!(second["access_token"].is_string());
+
+    // El token usado quedó invalidado, el nuevo sigue sirviendo
+    assert!(tokens::refresh(&first).is_none());
+    assert!(tokens::refresh(&refresh_token(&second)).is_some());
+}
+
+#[test]
+fn revoked_refresh_tokens_cannot_be_reused() {
+    let token = refresh_token(&tokens::issue("leaving").unwrap());
+    tokens::revoke(&token);
+    assert!(tokens::refresh(&token).is_none());
+}
+
+#[test]
+fn changing_the_password_revokes_every_refresh_token() {
+    users::register("resetting", "old-password").unwrap();
+    let stolen = refresh_token(&tokens::issue("resetting").unwrap());
+    let other_device = refresh_token(&tokens::issue("resetting").unwrap());
+    let someone_else = refresh_token(&tokens::issue("bystander").unwrap());
+
+    let body = r#"{"old_password":"old-password","new_password":"new-password"}"#;
+    let raw = format!("POST /password HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+    let mut request = parse_request(&raw).unwrap();
+    request.identity = Some(Identity { subject: "resetting".to_string(), roles: vec!["member".to_string()] });
+    assert_eq!(app::change_password_controller(request).status_code, 200);
+
+    assert!(tokens::refresh(&stolen).is_none());
+    assert!(tokens::refresh(&other_device).is_none());
+    assert!(tokens::refresh(&someone_else).is_some());
+}
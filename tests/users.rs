@@ -0,0 +1,50 @@
+// Bloqueo de cuentas por intentos fallidos de login.
+// Cada intento calcula un hash PBKDF2 a propósito lento, por eso los tests hacen los mínimos.
+extern crate httprust;
+use httprust::app::users::{self, AuthError};
+use std::thread;
+use std::time::Duration;
+
+const PASSWORD: &str = "correct horse";
+// Alcanza para verificar el bloqueo antes de que venza aunque el hash sea lento (sin optimizar)
+const LOCKOUT: Duration = Duration::from_secs(20);
+
+fn create(username: &str) {
+    users::set_lockout_duration(LOCKOUT);
+    users::register(username, PASSWORD).unwrap();
+}
+
+fn fail(username: &str, times: u32) {
+    for _ in 0..times {
+        assert!(matches!(users::authenticate(username, "wrong password"), Err(AuthError::InvalidCredentials)));
+    }
+}
+
+#[test]
+fn accounts_lock_after_too_many_failures_until_the_lockout_ends() {
+    create("locked");
+    fail("locked", 5);
+
+    // La contraseña correcta no alcanza mientras dure el bloqueo
+    let remaining = match users::authenticate("locked", PASSWORD) {
+        Err(AuthError::Locked(remaining)) => remaining,
+        other => panic!("expected a locked account, got {:?}", other),
+    };
+    // Una incorrecta responde igual que un usuario que no existe
+    fail("locked", 1);
+    fail("nobody", 1);
+
+    thread::sleep(remaining);
+    assert_eq!(users::authenticate("locked", PASSWORD).unwrap(), "locked");
+}
+
+#[test]
+fn a_successful_login_resets_the_failure_count() {
+    create("forgetful");
+    fail("forgetful", 4);
+    assert_eq!(users::authenticate("forgetful", PASSWORD).unwrap(), "forgetful");
+
+    // Sin el reinicio este sería el quinto fallo y la cuenta quedaría bloqueada
+    fail("forgetful", 1);
+    assert_eq!(users::authenticate("forgetful", PASSWORD).unwrap(), "forgetful");
+}
@@ -0,0 +1,145 @@
+// Frames de RFC 6455 contra un WebSocket conectado a un cliente de prueba por un socketpair
+#![cfg(unix)]
+extern crate httprust;
+use httprust::http::connection::Stream;
+use httprust::http::websocket::{Message, WebSocket, CLOSE_PROTOCOL_ERROR, CLOSE_TOO_BIG};
+use std::io::{BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+// WebSocket del servidor y el otro extremo de la conexión
+fn socket() -> (WebSocket, UnixStream) {
+    let (server, client) = UnixStream::pair().unwrap();
+    let writer: Stream = Box::new(server.try_clone().unwrap());
+    let reader: Stream = Box::new(server);
+    (WebSocket::new(BufReader::new(reader), writer), client)
+}
+
+// Header de un frame con la longitud codificada según su tamaño
+fn frame_header(fin: bool, opcode: u8, masked: bool, length: usize) -> Vec<u8> {
+    let mut header = vec![if fin { 0x80 | opcode } else { opcode }];
+    let mask_bit = if masked { 0x80 } else { 0 };
+    match length {
+        len if len < 126 => header.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            header.push(mask_bit | 126);
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            header.push(mask_bit | 127);
+            header.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    header
+}
+
+// Frame enmascarado como los que envía un navegador
+fn client_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = frame_header(fin, opcode, true, payload.len());
+    frame.extend_from_slice(&MASK);
+    frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ MASK[i % 4]));
+    frame
+}
+
+// Lee un frame del servidor: (opcode, payload)
+fn read_server_frame(client: &mut UnixStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 2];
+    client.read_exact(&mut header).unwrap();
+    assert_eq!(header[1] & 0x80, 0, "server frames must not be masked");
+    assert!(header[1] & 0x7F < 126, "expected a short frame");
+    let mut payload = vec![0u8; (header[1] & 0x7F) as usize];
+    client.read_exact(&mut payload).unwrap();
+    (header[0] & 0x0F, payload)
+}
+
+fn close_code(payload: &[u8]) -> u16 {
+    u16::from_be_bytes([payload[0], payload[1]])
+}
+
+#[test]
+fn unmasks_client_frames() {
+    let (mut socket, mut client) = socket();
+    client.write_all(&client_frame(true, 0x1, b"hello websocket")).unwrap();
+    assert_eq!(socket.recv().unwrap(), Message::Text("hello websocket".to_string()));
+
+    let data: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    client.write_all(&client_frame(true, 0x2, &data)).unwrap();
+    assert_eq!(socket.recv().unwrap(), Message::Binary(data));
+}
+
+#[test]
+fn rejects_unmasked_client_frames() {
+    let (mut socket, mut client) = socket();
+    let mut frame = frame_header(true, 0x1, false, 2);
+    frame.extend_from_slice(b"hi");
+    client.write_all(&frame).unwrap();
+
+    assert!(socket.recv().is_err());
+    let (opcode, payload) = read_server_frame(&mut client);
+    assert_eq!(opcode, 0x8);
+    assert_eq!(close_code(&payload), CLOSE_PROTOCOL_ERROR);
+}
+
+#[test]
+fn joins_fragments_and_answers_pings_in_between() {
+    let (mut socket, mut client) = socket();
+    client.write_all(&client_frame(false, 0x1, b"Hel")).unwrap();
+    client.write_all(&client_frame(true, 0x9, b"ping")).unwrap();
+    client.write_all(&client_frame(false, 0x0, b"lo, ")).unwrap();
+    client.write_all(&client_frame(true, 0x0, "wörld".as_bytes())).unwrap();
+
+    assert_eq!(socket.recv().unwrap(), Message::Text("Hello, wörld".to_string()));
+    assert_eq!(read_server_frame(&mut client), (0xA, b"ping".to_vec()));
+}
+
+#[test]
+fn rejects_continuation_without_a_first_fragment() {
+    let (mut socket, mut client) = socket();
+    client.write_all(&client_frame(true, 0x0, b"orphan")).unwrap();
+
+    assert!(socket.recv().is_err());
+    let (_, payload) = read_server_frame(&mut client);
+    assert_eq!(close_code(&payload), CLOSE_PROTOCOL_ERROR);
+}
+
+#[test]
+fn rejects_frames_over_the_size_limit() {
+    let (mut socket, mut client) = socket();
+    // Basta con el header, el payload no se llega a leer
+    client.write_all(&frame_header(true, 0x2, true, MAX_MESSAGE_SIZE + 1)).unwrap();
+
+    assert!(socket.recv().is_err());
+    let (_, payload) = read_server_frame(&mut client);
+    assert_eq!(close_code(&payload), CLOSE_TOO_BIG);
+}
+
+#[test]
+fn rejects_fragmented_messages_over_the_size_limit() {
+    let (mut socket, mut client) = socket();
+    let mut writer = client.try_clone().unwrap();
+    // El socketpair no guarda 16 MiB, entonces el cliente escribe en otro thread
+    let sender = thread::spawn(move || {
+        writer.write_all(&client_frame(false, 0x2, &vec![0u8; MAX_MESSAGE_SIZE])).unwrap();
+        writer.write_all(&client_frame(true, 0x0, b"!")).unwrap();
+    });
+
+    assert!(socket.recv().is_err());
+    sender.join().unwrap();
+    let (_, payload) = read_server_frame(&mut client);
+    assert_eq!(close_code(&payload), CLOSE_TOO_BIG);
+}
+
+#[test]
+fn close_reason_is_cut_at_a_char_boundary() {
+    let (mut socket, mut client) = socket();
+    socket.close(1000, &"é".repeat(100)).unwrap();
+
+    let (opcode, payload) = read_server_frame(&mut client);
+    assert_eq!(opcode, 0x8);
+    assert!(payload.len() <= 125);
+    let reason = String::from_utf8(payload[2..].to_vec()).expect("close reason must be valid UTF-8");
+    assert_eq!(reason, "é".repeat(61));
+}